@@ -4,10 +4,18 @@ pub mod math;
 pub mod render_context;
 pub mod renderer;
 pub mod scene;
+pub mod accessor;
+pub mod animation;
 pub mod material;
 pub mod texture;
 pub mod sampler;
 pub mod onb;
 pub mod transform;
 pub mod light;
+pub mod environment;
+pub mod output;
+pub mod frame_buffer;
+pub mod render_state;
+pub mod aov;
+pub mod tone_mapping;
 pub mod profile;
\ No newline at end of file
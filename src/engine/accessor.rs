@@ -0,0 +1,177 @@
+use gltf::accessor::{Accessor, DataType};
+use glam::{Vec2, Vec3A, Vec4};
+
+use std::fmt;
+
+#[derive(Debug)]
+pub enum AccessorError {
+    // Buffer index that was not loaded
+    MissingBuffer(usize),
+    // Required attribute a primitive does not have
+    MissingAttribute(String),
+    // The accessor with this index reads past the end of its buffer
+    OutOfBounds(usize),
+    // The accessor with this index has a component type, count or values the loader can not use
+    Invalid(usize, String),
+}
+
+impl fmt::Display for AccessorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AccessorError::MissingBuffer(index) => write!(f, "buffer {} is not loaded", index),
+            AccessorError::MissingAttribute(name) => write!(f, "missing {} attribute", name),
+            AccessorError::OutOfBounds(index) => write!(f, "accessor {} reads past the end of its buffer", index),
+            AccessorError::Invalid(index, message) => write!(f, "accessor {}: {}", index, message),
+        }
+    }
+}
+
+// Reads glTF accessors whatever their component type, normalization, offsets, stride or sparse
+// substitution
+pub struct AccessorReader<'a> {
+    buffers: &'a [Vec<u8>],
+}
+
+impl<'a> AccessorReader<'a> {
+    pub fn new(buffers: &'a [Vec<u8>]) -> Self {
+        Self {
+            buffers: buffers,
+        }
+    }
+
+    // Every component as a float, integer components are converted to [0, 1] or [-1, 1] when the
+    // accessor is normalized and kept as their value otherwise
+    pub fn read_floats(&self, accessor: &Accessor) -> Result<Vec<f32>, AccessorError> {
+        let normalized = accessor.normalized();
+        self.read(accessor, |bytes, data_type| decode_float(bytes, data_type, normalized))
+    }
+
+    pub fn read_vec2(&self, accessor: &Accessor) -> Result<Vec<Vec2>, AccessorError> {
+        Ok(self.read_floats(accessor)?.chunks_exact(2).map(|value| Vec2::new(value[0], value[1])).collect())
+    }
+
+    pub fn read_vec3(&self, accessor: &Accessor) -> Result<Vec<Vec3A>, AccessorError> {
+        Ok(self.read_floats(accessor)?.chunks_exact(3).map(|value| Vec3A::new(value[0], value[1], value[2])).collect())
+    }
+
+    pub fn read_vec4(&self, accessor: &Accessor) -> Result<Vec<Vec4>, AccessorError> {
+        Ok(self.read_floats(accessor)?.chunks_exact(4).map(|value| Vec4::new(value[0], value[1], value[2], value[3])).collect())
+    }
+
+    // Unsigned integer scalars such as vertex indices
+    pub fn read_indices(&self, accessor: &Accessor) -> Result<Vec<u32>, AccessorError> {
+        match accessor.data_type() {
+            DataType::U8 | DataType::U16 | DataType::U32 => {},
+            data_type => return Err(AccessorError::Invalid(accessor.index(),
+                format!("indices can not have {:?} components", data_type))),
+        }
+
+        self.read(accessor, |bytes, data_type| decode_unsigned(bytes, data_type))
+    }
+
+    fn read<T: Copy + Default>(&self, accessor: &Accessor, decode: impl Fn(&[u8], DataType) -> T) -> Result<Vec<T>, AccessorError> {
+        let data_type = accessor.data_type();
+        let components = accessor.dimensions().multiplicity();
+        let component_size = data_type.size();
+        let element_size = components * component_size;
+        let count = accessor.count();
+
+        // Accessors without a buffer view are zeros, possibly replaced by sparse values
+        let mut values = vec![T::default(); count * components];
+
+        if let Some(view) = accessor.view() {
+            let buffer = self.buffer(view.buffer().index())?;
+            let stride = view.stride().unwrap_or(element_size);
+            let start = view.offset() + accessor.offset();
+
+            if count > 0 && start + stride * (count - 1) + element_size > buffer.len().min(view.offset() + view.length()) {
+                return Err(AccessorError::OutOfBounds(accessor.index()));
+            }
+
+            for element in 0..count {
+                let offset = start + element * stride;
+                for component in 0..components {
+                    let position = offset + component * component_size;
+                    values[element * components + component] = decode(&buffer[position..position + component_size], data_type);
+                }
+            }
+        }
+
+        if let Some(sparse) = accessor.sparse() {
+            let indices = sparse.indices();
+            let index_type = match indices.index_type() {
+                gltf::accessor::sparse::IndexType::U8 => DataType::U8,
+                gltf::accessor::sparse::IndexType::U16 => DataType::U16,
+                gltf::accessor::sparse::IndexType::U32 => DataType::U32,
+            };
+            let indices_view = indices.view();
+            let indices_buffer = self.buffer(indices_view.buffer().index())?;
+            let indices_start = indices_view.offset() + indices.offset() as usize;
+
+            let values_view = sparse.values().view();
+            let values_buffer = self.buffer(values_view.buffer().index())?;
+            let values_start = values_view.offset() + sparse.values().offset() as usize;
+
+            let sparse_count = sparse.count() as usize;
+            if indices_start + sparse_count * index_type.size() > indices_buffer.len() ||
+                values_start + sparse_count * element_size > values_buffer.len() {
+                return Err(AccessorError::OutOfBounds(accessor.index()));
+            }
+
+            for i in 0..sparse_count {
+                let index_position = indices_start + i * index_type.size();
+                let element = decode_unsigned(&indices_buffer[index_position..index_position + index_type.size()], index_type) as usize;
+                if element >= count {
+                    return Err(AccessorError::OutOfBounds(accessor.index()));
+                }
+
+                // Sparse values are tightly packed
+                for component in 0..components {
+                    let position = values_start + i * element_size + component * component_size;
+                    values[element * components + component] = decode(&values_buffer[position..position + component_size], data_type);
+                }
+            }
+        }
+
+        Ok(values)
+    }
+
+    fn buffer(&self, index: usize) -> Result<&'a Vec<u8>, AccessorError> {
+        match self.buffers.get(index) {
+            Some(buffer) => Ok(buffer),
+            None => Err(AccessorError::MissingBuffer(index)),
+        }
+    }
+}
+
+fn decode_float(bytes: &[u8], data_type: DataType, normalized: bool) -> f32 {
+    match data_type {
+        DataType::I8 => {
+            let value = bytes[0] as i8 as f32;
+            if normalized {(value / i8::MAX as f32).max(-1.0)} else {value}
+        },
+        DataType::U8 => {
+            let value = bytes[0] as f32;
+            if normalized {value / u8::MAX as f32} else {value}
+        },
+        DataType::I16 => {
+            let value = i16::from_le_bytes([bytes[0], bytes[1]]) as f32;
+            if normalized {(value / i16::MAX as f32).max(-1.0)} else {value}
+        },
+        DataType::U16 => {
+            let value = u16::from_le_bytes([bytes[0], bytes[1]]) as f32;
+            if normalized {value / u16::MAX as f32} else {value}
+        },
+        DataType::U32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32,
+        DataType::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+    }
+}
+
+fn decode_unsigned(bytes: &[u8], data_type: DataType) -> u32 {
+    match data_type {
+        DataType::U8 => bytes[0] as u32,
+        DataType::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as u32,
+        DataType::U32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        _ => 0,
+    }
+}
@@ -0,0 +1,164 @@
+use glam::{Vec3, Vec4, Quat, Mat4};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Interpolation {
+    Step,
+    Linear,
+    CubicSpline,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Property {
+    Translation,
+    Rotation,
+    Scale,
+}
+
+// Keyframes of one property of a node
+#[derive(Clone)]
+pub struct Channel {
+    pub node: usize,
+    pub property: Property,
+    pub interpolation: Interpolation,
+    // Seconds, increasing
+    pub times: Vec<f32>,
+    // xyz for translations and scales, xyzw quaternions for rotations. Cubic splines have an in
+    // tangent, the value and an out tangent for every key
+    pub values: Vec<Vec4>,
+}
+
+impl Channel {
+    // Value at `time`, held at the first and last keys outside of them
+    pub fn sample(&self, time: f32) -> Vec4 {
+        let count = self.times.len();
+        if count == 0 {
+            return Vec4::ZERO;
+        }
+
+        let next = self.times.partition_point(|key_time| *key_time <= time);
+        if next == 0 {
+            return self.value(0);
+        }
+        if next == count {
+            return self.value(count - 1);
+        }
+
+        let key = next - 1;
+        let duration = self.times[next] - self.times[key];
+        let t = if duration > 0.0 {(time - self.times[key]) / duration} else {0.0};
+
+        match self.interpolation {
+            Interpolation::Step => self.value(key),
+            Interpolation::Linear => match self.property {
+                Property::Rotation => Vec4::from(Quat::from_vec4(self.value(key)).slerp(Quat::from_vec4(self.value(next)), t)),
+                _ => self.value(key).lerp(self.value(next), t),
+            },
+            Interpolation::CubicSpline => {
+                // Hermite spline, tangents are per second
+                let t2 = t * t;
+                let t3 = t2 * t;
+                let value = self.value(key) * (2.0 * t3 - 3.0 * t2 + 1.0)
+                    + self.values[key * 3 + 2] * duration * (t3 - 2.0 * t2 + t)
+                    + self.value(next) * (-2.0 * t3 + 3.0 * t2)
+                    + self.values[next * 3] * duration * (t3 - t2);
+
+                match self.property {
+                    Property::Rotation => value.normalize_or_zero(),
+                    _ => value,
+                }
+            },
+        }
+    }
+
+    fn value(&self, key: usize) -> Vec4 {
+        match self.interpolation {
+            Interpolation::CubicSpline => self.values[key * 3 + 1],
+            _ => self.values[key],
+        }
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.times.last().copied().unwrap_or(0.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct NodeTransform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl NodeTransform {
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+#[derive(Clone)]
+pub struct AnimationNode {
+    // Transform relative to the parent when no channel overrides it
+    pub rest: NodeTransform,
+    pub children: Vec<usize>,
+}
+
+// Node hierarchy of the loaded glTF files and the channels of all of their animations, played
+// together
+#[derive(Clone)]
+pub struct Animation {
+    // By object id
+    pub nodes: Vec<AnimationNode>,
+    // Nodes the scenes start from
+    pub roots: Vec<usize>,
+    pub channels: Vec<Channel>,
+}
+
+impl Animation {
+    // Time the last key of any channel is at
+    pub fn duration(&self) -> f32 {
+        self.channels.iter().map(|channel| channel.duration()).fold(0.0, f32::max)
+    }
+
+    // World transform at `time` of every node that is animated or has an animated ancestor, None
+    // for the nodes that stay where they were loaded
+    pub fn world_matrices(&self, time: f32) -> Vec<Option<Mat4>> {
+        let mut transforms: Vec<NodeTransform> = self.nodes.iter().map(|node| node.rest).collect();
+        let mut animated = vec![false; self.nodes.len()];
+
+        for channel in self.channels.iter() {
+            let transform = match transforms.get_mut(channel.node) {
+                Some(transform) => transform,
+                None => continue,
+            };
+
+            let value = channel.sample(time);
+            match channel.property {
+                Property::Translation => transform.translation = value.truncate(),
+                Property::Rotation => transform.rotation = Quat::from_vec4(value).normalize(),
+                Property::Scale => transform.scale = value.truncate(),
+            }
+            animated[channel.node] = true;
+        }
+
+        let mut matrices = vec![None; self.nodes.len()];
+        // Node, world matrix of its parent and whether an ancestor is animated
+        let mut stack: Vec<(usize, Mat4, bool)> = self.roots.iter().map(|root| (*root, Mat4::IDENTITY, false)).collect();
+        while let Some((node, parent_matrix, parent_animated)) = stack.pop() {
+            if node >= self.nodes.len() {
+                continue;
+            }
+
+            let matrix = parent_matrix * transforms[node].matrix();
+            let moved = parent_animated || animated[node];
+            if moved {
+                matrices[node] = Some(matrix);
+            }
+
+            for child in self.nodes[node].children.iter() {
+                stack.push((*child, matrix, moved));
+            }
+        }
+
+        matrices
+    }
+}
@@ -0,0 +1,132 @@
+use crate::engine::tone_mapping::*;
+
+use glam::{Vec3A};
+
+// Auxiliary per-pixel buffers written next to the beauty image
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum AOV {
+    Albedo,
+    Normal,
+    Depth,
+    Position,
+    UV,
+    MaterialId,
+    ObjectId,
+    // Emission and light sampled at the first surface, `Indirect` is everything else
+    Direct,
+    Indirect,
+}
+
+impl AOV {
+    pub const ALL: [AOV; 9] = [
+        AOV::Albedo, AOV::Normal, AOV::Depth, AOV::Position, AOV::UV,
+        AOV::MaterialId, AOV::ObjectId, AOV::Direct, AOV::Indirect,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        AOV::ALL.iter().copied().find(|aov| aov.name() == name.to_lowercase())
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AOV::Albedo => "albedo",
+            AOV::Normal => "normal",
+            AOV::Depth => "depth",
+            AOV::Position => "position",
+            AOV::UV => "uv",
+            AOV::MaterialId => "material_id",
+            AOV::ObjectId => "object_id",
+            AOV::Direct => "direct",
+            AOV::Indirect => "indirect",
+        }
+    }
+
+    // EXR channel names, scalar buffers only keep the first component
+    pub fn channels(&self) -> &'static [&'static str] {
+        match self {
+            AOV::Depth => &["Z"],
+            AOV::MaterialId | AOV::ObjectId => &["X"],
+            AOV::UV => &["U", "V"],
+            _ => &["R", "G", "B"],
+        }
+    }
+
+    // Ids are taken from a single sample, averaging them would produce ids of nothing
+    pub fn filtered(&self) -> bool {
+        *self != AOV::MaterialId && *self != AOV::ObjectId
+    }
+
+    // Maps the buffer to [0, 1] for 8 bit images, color buffers go through the beauty tone mapping
+    pub fn visualize(&self, value: Vec3A, tone_mapping: &ToneMapping) -> Vec3A {
+        match self {
+            AOV::Albedo | AOV::Direct | AOV::Indirect => tone_mapping.apply(value),
+            AOV::Normal => 0.5 * (value + Vec3A::ONE),
+            AOV::Depth => Vec3A::splat(if value.x > 0.0 {1.0 / (1.0 + value.x)} else {0.0}),
+            AOV::Position => 0.5 * (value / (Vec3A::ONE + value.abs()) + Vec3A::ONE),
+            AOV::UV => Vec3A::new(value.x.rem_euclid(1.0), value.y.rem_euclid(1.0), 0.0),
+            AOV::MaterialId | AOV::ObjectId => id_color(value.x as u32),
+        }
+    }
+}
+
+// First hit data of a single camera path. Misses leave the defaults, ids are 1-based so 0 is background
+#[derive(Copy, Clone)]
+pub struct AOVSample {
+    pub albedo: Vec3A,
+    pub normal: Vec3A,
+    pub depth: f32,
+    pub position: Vec3A,
+    pub uv: Vec3A,
+    pub material_id: u32,
+    pub object_id: u32,
+    pub direct: Vec3A,
+    pub indirect: Vec3A,
+}
+
+impl AOVSample {
+    pub fn new() -> Self {
+        Self {
+            albedo: Vec3A::ZERO,
+            normal: Vec3A::ZERO,
+            depth: 0.0,
+            position: Vec3A::ZERO,
+            uv: Vec3A::ZERO,
+            material_id: 0,
+            object_id: 0,
+            direct: Vec3A::ZERO,
+            indirect: Vec3A::ZERO,
+        }
+    }
+
+    pub fn get(&self, aov: AOV) -> Vec3A {
+        match aov {
+            AOV::Albedo => self.albedo,
+            AOV::Normal => self.normal,
+            AOV::Depth => Vec3A::splat(self.depth),
+            AOV::Position => self.position,
+            AOV::UV => self.uv,
+            AOV::MaterialId => Vec3A::splat(self.material_id as f32),
+            AOV::ObjectId => Vec3A::splat(self.object_id as f32),
+            AOV::Direct => self.direct,
+            AOV::Indirect => self.indirect,
+        }
+    }
+}
+
+// Stable pseudo random color per id
+fn id_color(id: u32) -> Vec3A {
+    if id == 0 {
+        return Vec3A::ZERO;
+    }
+
+    let mut hash = id.wrapping_mul(0x9E3779B9);
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85EBCA6B);
+    hash ^= hash >> 13;
+
+    Vec3A::new(
+        (hash & 0xFF) as f32 / 255.0,
+        ((hash >> 8) & 0xFF) as f32 / 255.0,
+        ((hash >> 16) & 0xFF) as f32 / 255.0,
+    )
+}
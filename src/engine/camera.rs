@@ -7,6 +7,8 @@ use crate::engine::onb::*;
 
 pub trait Camera {
     fn get_ray(&self, u : f32, v : f32) -> Ray;
+    // Angle covered by one pixel of an image `image_height` pixels high
+    fn pixel_spread(&self, image_height: u32) -> f32;
     fn aspect_ratio(&self) -> f32;
     fn name(&self) -> String;
 }
@@ -41,26 +43,40 @@ impl PerspectiveCamera {
         let height = 2.0 * h;
         let width = height * aspect_ratio;
 
+        Self {
+            camera: CommonCamera{ aspect_ratio: aspect_ratio,
+                width: width,
+                height: height,
+                focal_length: 1.0,
+                transform: Self::view_transform(transform),
+                name: String::from_str(name).unwrap() }
+        }
+    }
+
+    // Same camera placed by `transform`
+    pub fn transformed(&self, transform: &Mat4) -> Self {
+        Self {
+            camera: CommonCamera{ aspect_ratio: self.camera.aspect_ratio,
+                width: self.camera.width,
+                height: self.camera.height,
+                focal_length: self.camera.focal_length,
+                transform: Self::view_transform(transform),
+                name: self.camera.name.clone() }
+        }
+    }
+
+    fn view_transform(transform: &Mat4) -> Transform {
         let forward = Vec3A::from(transform.mul_vec4(Vec4::new(0.0, 0.0, -1.0, 0.0))).normalize();
         let right = Vec3A::from(transform.mul_vec4(Vec4::new(1.0, 0.0, 0.0, 0.0))).normalize();
         let up = right.cross(forward).normalize();
 
         let origin = Vec3A::from(transform.mul_vec4(Vec4::new(0.0, 0.0, 0.0, 1.0)));
 
-        let transform = Transform{
+        Transform{
             basis: ONB{x : right, y: up, z: forward},
             translation: origin,
             scale: Vec3A::ONE,
             model_matrix: *transform,
-        };
-
-        Self {
-            camera: CommonCamera{ aspect_ratio: aspect_ratio,
-                width: width,
-                height: height,
-                focal_length: 1.0,
-                transform: transform,
-                name: String::from_str(name).unwrap() }
         }
     }
 }
@@ -73,10 +89,15 @@ impl Camera for PerspectiveCamera{
             self.camera.focal_length));
         Ray{
             origin : self.camera.transform.translation, 
-            direction : pixel_position.normalize()
+            direction : pixel_position.normalize(),
+            cone : RayCone::ZERO,
         }
     }
 
+    fn pixel_spread(&self, image_height: u32) -> f32 {
+        2.0 * (self.camera.height / (2.0 * self.camera.focal_length * image_height as f32)).atan()
+    }
+
     fn aspect_ratio(&self) -> f32 {
         self.camera.aspect_ratio
     }
@@ -0,0 +1,156 @@
+use crate::engine::math::distribution::*;
+use crate::engine::math::utils::*;
+
+use glam::{Vec3A};
+use crate::engine::sampler::*;
+use crate::engine::texture::Texture;
+
+use std::path::Path;
+use std::ffi::OsStr;
+use std::fs;
+
+pub trait Environment {
+    // Radiance arriving along a ray escaping the scene in `direction`
+    fn radiance(&self, direction: Vec3A) -> Vec3A;
+
+    // Whether the environment can be sampled as a light through `EnvironmentPDF`
+    fn importance_sampled(&self) -> bool {
+        false
+    }
+
+    fn pdf(&self, direction: Vec3A) -> f32 {
+        0.0
+    }
+
+    fn generate(&self, sampler: &mut dyn SampleGenerator) -> Vec3A {
+        Vec3A::Y
+    }
+}
+
+pub struct ConstantEnvironment {
+    pub color: Vec3A,
+}
+
+impl Environment for ConstantEnvironment {
+    fn radiance(&self, direction: Vec3A) -> Vec3A {
+        self.color
+    }
+}
+
+// White to red sky gradient the renderer always used before environments were configurable
+pub struct GradientEnvironment {
+
+}
+
+impl Environment for GradientEnvironment {
+    fn radiance(&self, direction: Vec3A) -> Vec3A {
+        let t = 0.5 * (direction.y + 1.0);
+        (1.0 - t) * Vec3A::new(1.0, 1.0, 1.0) + t * Vec3A::new(0.9, 0.3, 0.3)
+    }
+}
+
+// Equirectangular (latitude-longitude) map, +Y is up
+pub struct EnvironmentMap {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Vec3A>,
+    // Rotation around the up axis in radians
+    pub rotation: f32,
+    pub intensity: f32,
+
+    distribution: Distribution2D,
+}
+
+impl EnvironmentMap {
+    pub fn new(width: usize, height: usize, pixels: Vec<Vec3A>, rotation: f32, intensity: f32) -> Self {
+        // Weight luminance by sin(theta) so the poles are not oversampled
+        let mut function = vec![0.0; width * height];
+        for y in 0..height {
+            let sin_theta = (std::f32::consts::PI * (y as f32 + 0.5) / height as f32).sin();
+            for x in 0..width {
+                function[y * width + x] = luminance(pixels[y * width + x]) * sin_theta;
+            }
+        }
+
+        let distribution = Distribution2D::new(&function, width, height);
+
+        Self {
+            width: width,
+            height: height,
+            pixels: pixels,
+            rotation: rotation,
+            intensity: intensity,
+            distribution: distribution,
+        }
+    }
+
+    pub fn load(path: &str, rotation: f32, intensity: f32) -> Result<Self, String> {
+        let ext = Path::new(path)
+            .extension()
+            .and_then(OsStr::to_str)
+            .unwrap_or("");
+
+        let data = fs::read(path).map_err(|error| error.to_string())?;
+        let texture = match ext.to_lowercase().as_str() {
+            "hdr" => Texture::load_hdr(&data)?,
+            "exr" => Texture::load_exr(&data)?,
+            _ => return Err(String::from("unsupported environment map format, expected .hdr or .exr")),
+        };
+
+        let level = texture.base_level();
+        let pixels = (0..level.height)
+            .flat_map(|y| (0..level.width).map(move |x| Vec3A::from(level.texel(x, y).truncate())))
+            .collect();
+
+        Ok(Self::new(level.width as usize, level.height as usize, pixels, rotation, intensity))
+    }
+
+    fn direction_to_uv(&self, direction: Vec3A) -> (f32, f32) {
+        let direction = direction.normalize();
+        let phi = direction.x.atan2(-direction.z) - self.rotation;
+        let theta = direction.y.clamp(-1.0, 1.0).acos();
+
+        let u = (phi / (2.0 * std::f32::consts::PI) + 0.5).rem_euclid(1.0);
+        let v = theta / std::f32::consts::PI;
+
+        (u, v)
+    }
+
+    fn uv_to_direction(&self, u: f32, v: f32) -> Vec3A {
+        let phi = (u - 0.5) * 2.0 * std::f32::consts::PI + self.rotation;
+        let theta = v * std::f32::consts::PI;
+
+        Vec3A::new(theta.sin() * phi.sin(), theta.cos(), -theta.sin() * phi.cos())
+    }
+}
+
+impl Environment for EnvironmentMap {
+    fn radiance(&self, direction: Vec3A) -> Vec3A {
+        let (u, v) = self.direction_to_uv(direction);
+        let x = ((u * self.width as f32) as usize).min(self.width - 1);
+        let y = ((v * self.height as f32) as usize).min(self.height - 1);
+
+        self.pixels[y * self.width + x] * self.intensity
+    }
+
+    fn importance_sampled(&self) -> bool {
+        self.distribution.marginal.integral > 0.0
+    }
+
+    fn pdf(&self, direction: Vec3A) -> f32 {
+        let (u, v) = self.direction_to_uv(direction);
+        let sin_theta = (v * std::f32::consts::PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+
+        self.distribution.pdf(u, v) / (2.0 * std::f32::consts::PI * std::f32::consts::PI * sin_theta)
+    }
+
+    fn generate(&self, sampler: &mut dyn SampleGenerator) -> Vec3A {
+        let (r1, r2): (f32, f32) = sampler.get_2d().into();
+
+        let (u, v, _) = self.distribution.sample_continuous(r1, r2);
+        self.uv_to_direction(u, v)
+    }
+}
@@ -0,0 +1,131 @@
+use crate::engine::aov::*;
+use crate::engine::output::*;
+use crate::engine::tone_mapping::*;
+use crate::engine::math::utils::*;
+
+use glam::{Vec3A};
+
+// Float accumulation buffer shared by the progressive passes. Holds per-pixel sums and sample
+// counts so pixels can be resolved at any point of the render
+pub struct FrameBuffer {
+    pub width: u32,
+    pub height: u32,
+    pub color: Vec<Vec3A>,
+    // Sums for filtered AOVs, the first sample for the others
+    pub aovs: Vec<(AOV, Vec<Vec3A>)>,
+    pub samples: Vec<u32>,
+    // Sum of the squared luminance of every sample, for the variance estimate
+    pub luminance_squared: Vec<f32>,
+}
+
+impl FrameBuffer {
+    pub fn new(width: u32, height: u32, aovs: &[AOV]) -> Self {
+        let pixel_count = (width * height) as usize;
+
+        Self {
+            width: width,
+            height: height,
+            color: vec![Vec3A::ZERO; pixel_count],
+            aovs: aovs.iter().map(|aov| (*aov, vec![Vec3A::ZERO; pixel_count])).collect(),
+            samples: vec![0; pixel_count],
+            luminance_squared: vec![0.0; pixel_count],
+        }
+    }
+
+    // Adds `count` samples summed into `color`, `aovs` and `luminance_squared` to pixel (x, y)
+    pub fn add(&mut self, x: u32, y: u32, color: Vec3A, aovs: &[Vec3A], luminance_squared: f32, count: u32) {
+        if x >= self.width || y >= self.height || count == 0 {
+            return;
+        }
+
+        let index = (y * self.width + x) as usize;
+        let first_samples = self.samples[index] == 0;
+
+        self.color[index] += color;
+        for ((aov, buffer), value) in self.aovs.iter_mut().zip(aovs) {
+            if aov.filtered() {
+                buffer[index] += *value;
+            } else if first_samples {
+                buffer[index] = *value;
+            }
+        }
+
+        self.samples[index] += count;
+        self.luminance_squared[index] += luminance_squared;
+    }
+
+    // Standard error of the pixel mean relative to its luminance, dark pixels are measured
+    // against a floor so they can converge
+    pub fn relative_error(&self, index: usize) -> f32 {
+        let count = self.samples[index] as f32;
+        if count < 2.0 {
+            return f32::MAX;
+        }
+
+        let mean = luminance(self.color[index]) / count;
+        let variance = ((self.luminance_squared[index] / count - mean * mean) * count / (count - 1.0)).max(0.0);
+
+        (variance / count).sqrt() / mean.max(0.01)
+    }
+
+    // Pixels that still need samples: below `min_samples` or above the error threshold
+    pub fn unconverged(&self, threshold: f32, min_samples: u32) -> Vec<bool> {
+        (0..self.samples.len())
+            .map(|index| self.samples[index] < min_samples || self.relative_error(index) > threshold)
+            .collect()
+    }
+
+    // Samples that bring every `active` pixel up to `target`
+    pub fn pass_cost(&self, active: &[bool], target: u32) -> u64 {
+        self.samples.iter().zip(active)
+            .filter(|(_, active)| **active)
+            .map(|(samples, _)| target.saturating_sub(*samples) as u64)
+            .sum()
+    }
+
+    // Highest sample count up to `target` the `active` pixels can be brought to without the whole
+    // image going over `budget` samples
+    pub fn budget_target(&self, active: &[bool], target: u32, budget: u64) -> u32 {
+        let spent: u64 = self.samples.iter().map(|samples| *samples as u64).sum();
+        let remaining = budget.saturating_sub(spent);
+
+        let (mut low, mut high) = (0, target);
+        while low < high {
+            let middle = high - (high - low) / 2;
+            if self.pass_cost(active, middle) <= remaining {
+                low = middle;
+            } else {
+                high = middle - 1;
+            }
+        }
+
+        low
+    }
+
+    // Per-pixel averages of the beauty and AOV buffers
+    pub fn resolve(&self) -> (Vec<Vec3A>, Vec<(AOV, Vec<Vec3A>)>) {
+        let scale = |index: usize| 1.0 / self.samples[index].max(1) as f32;
+
+        let color = self.color.iter().enumerate()
+            .map(|(index, color)| *color * scale(index))
+            .collect();
+
+        let aovs = self.aovs.iter()
+            .map(|(aov, buffer)| (*aov, buffer.iter().enumerate()
+                .map(|(index, value)| if aov.filtered() {*value * scale(index)} else {*value})
+                .collect()))
+            .collect();
+
+        (color, aovs)
+    }
+
+    pub fn save(&self, path: &str, format: OutputFormat, tone_mapping: &ToneMapping) -> Result<(), OutputError> {
+        let (color, aovs) = self.resolve();
+        save_render(path, format, self.width, self.height, &color, &aovs, tone_mapping)
+    }
+
+    pub fn save_heatmap(&self, path: &str) -> Result<(), OutputError> {
+        let samples: Vec<f32> = self.samples.iter().map(|samples| *samples as f32).collect();
+        save_heatmap(path, self.width, self.height, &samples)
+    }
+}
@@ -11,6 +11,7 @@ use super::aabb::*;
 use super::node::*;
 use rand::{Rng};
 
+#[derive(Clone)]
 pub struct BVH {
     pub primitives: Arc<Vec<Arc<dyn Traceable>>>,
 
@@ -114,6 +115,26 @@ impl BVH {
         self.subdivide(right_child_index);
     }
 
+    // Recomputes the node bounds around `primitives`, the ones the BVH was built with moved to new
+    // places. The tree is kept, which is much cheaper than a rebuild but gets slower to traverse the
+    // further primitives move from where they were
+    pub fn refit(&mut self, primitives: Arc<Vec<Arc<dyn Traceable>>>) {
+        self.primitives = primitives;
+        if self.primitives.is_empty() {
+            return;
+        }
+
+        // Children are always stored after their parent
+        for node_index in (0..self.nodes_used).rev() {
+            if self.nodes[node_index].is_leaf() {
+                self.update_node_bounds(node_index);
+            } else {
+                let left_child_index = self.nodes[node_index].left_node_or_primitive_index;
+                self.nodes[node_index].aabb = self.nodes[left_child_index].aabb.extend(&self.nodes[left_child_index + 1].aabb);
+            }
+        }
+    }
+
     fn calculate_best_split_plane(&self, node: &Node) -> (usize, f32, f32) {
         // determine split axis using SAH
         let mut best_axis = 0;
@@ -195,6 +216,14 @@ impl BVH {
     }
 
     pub fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32, node_index: usize) -> (Option<HitResult>, &dyn Traceable) {
+        match self.closest(ray, t_min, t_max, node_index) {
+            Some((hit_result, index)) => (Some(hit_result), self.primitive(index).as_ref()),
+            None => (None, self.primitive(0).as_ref()),
+        }
+    }
+
+    // Closest hit below `node_index` and its index into `primitive_indices`
+    fn closest(&self, ray: &Ray, t_min: f32, t_max: f32, node_index: usize) -> Option<(HitResult, usize)> {
         let mut stack: [*const Node; 256] = [std::ptr::null(); 256];
         let mut node: *const Node = &self.nodes[node_index];
         let mut stack_ptr = 0;
@@ -209,7 +238,9 @@ impl BVH {
             binormal : Vec3A::ZERO, 
             tangent : Vec3A::ZERO, 
             uvs: Vec::new(), 
-            front_face: false
+            uv_footprints: Vec::new(),
+            front_face: false,
+            primitive: 0,
         };
         let mut min_index = None;
 
@@ -274,14 +305,16 @@ impl BVH {
             }
         }
 
-        if min_index.is_some() {
-            return (Some(min_hit_result), self.primitive(min_index.unwrap()).as_ref());
-        } 
-        
-        return (None, self.primitive(0).as_ref());
+        min_index.map(|index| (min_hit_result, index))
     }
 
     pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> (Option<HitResult>, &dyn Traceable) {
         return self.intersect(ray, t_min, t_max, self.root_node_index);
     }
+
+    // Closest hit and the index into `primitives` of what was hit
+    pub fn hit_primitive(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(HitResult, usize)> {
+        self.closest(ray, t_min, t_max, self.root_node_index)
+            .map(|(hit_result, index)| (hit_result, self.primitive_indices[index]))
+    }
 }
\ No newline at end of file
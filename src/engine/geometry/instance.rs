@@ -0,0 +1,132 @@
+use crate::engine::math::ray::*;
+use crate::engine::geometry::traceable::*;
+use crate::engine::geometry::bvh::bvh::*;
+use glam::{Vec3A, Mat3A, Mat4};
+use crate::engine::sampler::*;
+use super::bvh::aabb::*;
+use crate::engine::material::*;
+use std::sync::*;
+
+// Placement of shared object space geometry in the scene. Rays are moved into object space to
+// traverse the bottom level BVH, so any number of instances share one copy of the triangles.
+// Instances are not sampled as lights, emissive geometry is kept in world space instead
+pub struct Instance {
+    pub material: Arc<dyn Material>,
+    pub bvh: Arc<BVH>,
+
+    pub object_to_world: Mat4,
+    pub world_to_object: Mat4,
+    normal_matrix: Mat3A,
+
+    aabb: AABB,
+    centroid: Vec3A,
+}
+
+impl Instance {
+    pub fn new(material: Arc<dyn Material>, bvh: Arc<BVH>, object_to_world: Mat4) -> Self {
+        let mut instance = Self {
+            material: material,
+            bvh: bvh,
+            object_to_world: Mat4::IDENTITY,
+            world_to_object: Mat4::IDENTITY,
+            normal_matrix: Mat3A::IDENTITY,
+            aabb: AABB::new(Vec3A::ZERO, Vec3A::ZERO),
+            centroid: Vec3A::ZERO,
+        };
+        instance.set_transform(object_to_world);
+
+        instance
+    }
+
+    pub fn set_transform(&mut self, object_to_world: Mat4) {
+        self.object_to_world = object_to_world;
+        self.world_to_object = object_to_world.inverse();
+        self.normal_matrix = Mat3A::from_mat4(object_to_world).inverse().transpose();
+
+        // World bounds of the corners of the object space bounds
+        let bounds = self.bvh.nodes[self.bvh.root_node_index].aabb;
+        self.aabb = AABB::new(Vec3A::MAX, Vec3A::MIN);
+        for corner in 0..8 {
+            let point = Vec3A::new(
+                if corner & 1 == 0 {bounds.min.x} else {bounds.max.x},
+                if corner & 2 == 0 {bounds.min.y} else {bounds.max.y},
+                if corner & 4 == 0 {bounds.min.z} else {bounds.max.z},
+            );
+            let point = object_to_world.transform_point3a(point);
+            self.aabb = AABB::new(self.aabb.min.min(point), self.aabb.max.max(point));
+        }
+        self.centroid = (self.aabb.min + self.aabb.max) * 0.5;
+    }
+
+    // `ray` in object space, None when the transform collapses its direction
+    fn object_ray(&self, ray: &Ray) -> Option<Ray> {
+        // The direction is not normalized so distances along the ray stay the same
+        let direction = self.world_to_object.transform_vector3a(ray.direction);
+        let direction_length = direction.length();
+        if direction_length == 0.0 {
+            return None;
+        }
+
+        // Footprints are measured in object space units, scaled along the ray direction
+        let scale = ray.direction.length() / direction_length;
+        Some(Ray {
+            origin: self.world_to_object.transform_point3a(ray.origin),
+            direction: direction,
+            cone: RayCone {
+                width: ray.cone.width / scale,
+                spread: ray.cone.spread,
+            },
+        })
+    }
+}
+
+impl Traceable for Instance {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> (Option<HitResult>, &dyn Traceable) {
+        let object_ray = match self.object_ray(ray) {
+            Some(object_ray) => object_ray,
+            None => return (None, self),
+        };
+
+        let mut hit_result = match self.bvh.hit_primitive(&object_ray, t_min, t_max) {
+            Some((hit_result, primitive)) => HitResult{primitive: primitive, ..hit_result},
+            None => return (None, self),
+        };
+
+        hit_result.position = ray.at(hit_result.t);
+        hit_result.normal = (self.normal_matrix * hit_result.normal).normalize();
+        hit_result.tangent = self.object_to_world.transform_vector3a(hit_result.tangent).normalize_or_zero();
+        hit_result.binormal = self.object_to_world.transform_vector3a(hit_result.binormal).normalize_or_zero();
+
+        (Some(hit_result), self)
+    }
+
+    fn finalize(&self, ray: &Ray, hit_result: &mut HitResult) {
+        if let Some(object_ray) = self.object_ray(ray) {
+            self.bvh.primitives[hit_result.primitive].finalize(&object_ray, hit_result);
+        }
+    }
+
+    fn pdf(&self, _ray: &Ray, _t_min: f32, _t_max: f32) -> f32 {
+        0.0
+    }
+
+    fn random(&self, _origin: Vec3A, _sampler: &mut dyn SampleGenerator) -> Vec3A {
+        self.centroid
+    }
+
+    fn bounding_box(&self) -> &AABB {
+        &self.aabb
+    }
+
+    fn centroid(&self) -> &Vec3A {
+        &self.centroid
+    }
+
+    fn primitive_count(&self) -> usize {
+        self.bvh.primitives.len()
+    }
+
+    fn material(&self) -> &Arc<dyn Material> {
+        &self.material
+    }
+}
@@ -2,4 +2,5 @@ pub mod sphere;
 pub mod traceable;
 pub mod vertex;
 pub mod triangle;
+pub mod instance;
 pub mod bvh;
\ No newline at end of file
@@ -4,6 +4,8 @@ use glam::{Vec3A};
 use super::bvh::aabb::*;
 use crate::engine::material::*;
 use crate::engine::math::utils::*;
+use crate::engine::sampler::*;
+use crate::engine::onb::*;
 
 use std::{sync::*};
 
@@ -30,6 +32,16 @@ impl Sphere {
             aabb: aabb,
         }
     }
+
+    // Cosine of the half-angle of the cone the sphere subtends from `origin`
+    fn cos_theta_max(&self, origin: Vec3A) -> f32 {
+        let distance_squared = (self.position - origin).length_squared();
+        if distance_squared <= self.radius * self.radius {
+            return -1.0;
+        }
+
+        (1.0 - self.radius * self.radius / distance_squared).sqrt()
+    }
 }
 
 impl Traceable for Sphere {
@@ -66,7 +78,9 @@ impl Traceable for Sphere {
             binormal : normal, 
             tangent : normal, 
             uvs: Vec::new(), 
+            uv_footprints: Vec::new(),
             front_face: front_face,
+            primitive: 0,
         }), self)
     }
 
@@ -76,14 +90,23 @@ impl Traceable for Sphere {
             return 0.0;
         }
 
-        let cos_theta_max = (1.0 - self.radius * self.radius / (self.position - ray.origin).length_squared()).sqrt();
-        let solid_angle = 2.0 * std::f32::consts::PI * (1.0 - cos_theta_max);
+        let solid_angle = 2.0 * std::f32::consts::PI * (1.0 - self.cos_theta_max(ray.origin));
 
         return 1.0 / solid_angle;
     }
 
-    fn random(&self) -> Vec3A {
-        self.position + random_in_unit_sphere() * self.radius
+    fn random(&self, origin: Vec3A, sampler: &mut dyn SampleGenerator) -> Vec3A {
+        let to_center = self.position - origin;
+        let basis = ONB::build_from_z(to_center.normalize());
+        let direction = basis.get_position(random_in_cone(self.cos_theta_max(origin), sampler)).normalize();
+
+        // Nearest intersection along the sampled direction, the far one from inside the sphere.
+        // Directions at the edge of the cone graze the sphere, so rounding is clamped away
+        let projection = direction.dot(to_center);
+        let discriminant = (projection * projection - to_center.length_squared() + self.radius * self.radius).max(0.0).sqrt();
+        let t = if projection - discriminant > 0.0 {projection - discriminant} else {projection + discriminant};
+
+        origin + direction * t
     }
 
     fn bounding_box(&self) -> &AABB {
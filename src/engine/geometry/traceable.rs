@@ -4,14 +4,26 @@ use crate::engine::{math::ray::*, material::diffuse::DiffuseMaterial};
 use crate::engine::material::*;
 use std::sync::*;
 use super::bvh::aabb::*;
-use glam::{Vec3A};
+use crate::engine::sampler::*;
+use glam::{Vec2, Vec3A};
 
 pub trait Traceable {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> (Option<HitResult>, &dyn Traceable);
+    // Solid angle density of sampling `ray.direction` through `random` from `ray.origin`
     fn pdf(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32;
-    fn random(&self) -> Vec3A;
+    // Point on the surface, sampled as seen from `origin`
+    fn random(&self, origin: Vec3A, sampler: &mut dyn SampleGenerator) -> Vec3A;
     fn bounding_box(&self) -> &AABB;
     fn centroid(&self) -> &Vec3A;
+    // Fills in what only the closest hit along `ray` needs, like the UV footprints. `hit` runs for
+    // every candidate along the ray and for shadow rays, which never use them
+    fn finalize(&self, _ray: &Ray, _hit_result: &mut HitResult) {
+    }
+
+    // Primitives the traceable is made of, more than one for instances
+    fn primitive_count(&self) -> usize {
+        1
+    }
 
     fn material(&self) -> &Arc<dyn Material>;
 }
@@ -24,7 +36,11 @@ pub struct HitResult {
     pub binormal: Vec3A,
     pub tangent: Vec3A,
     pub uvs: Vec<Vec3A>,
+    // Axes of the ray cone footprint in every UV set of `uvs`, empty until `Traceable::finalize`
+    pub uv_footprints: Vec<[Vec2; 2]>,
     pub front_face: bool,
+    // Index of the primitive hit inside an instance, for `Traceable::finalize`
+    pub primitive: usize,
 }
 
 impl HitResult {
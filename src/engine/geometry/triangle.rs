@@ -1,8 +1,8 @@
 use crate::engine::math::ray::*;
 use crate::engine::geometry::traceable::*;
 use crate::engine::geometry::vertex::*;
-use glam::{Vec3A};
-use rand::{Rng};
+use glam::{Vec2, Vec3A, Mat3A, Mat4};
+use crate::engine::sampler::*;
 use super::bvh::aabb::*;
 use crate::engine::material::*;
 use std::sync::*;
@@ -13,6 +13,7 @@ pub struct Triangle {
 
     pub vertices: [Vertex; 3],
     pub centroid: Vec3A,
+    pub area: f32,
 }
 
 impl Triangle {
@@ -27,14 +28,75 @@ impl Triangle {
         );
 
         let centroid = (v1.position + v2.position + v3.position) / 3.0;
+        let area = 0.5 * (v2.position - v1.position).cross(v3.position - v1.position).length();
 
         Self {
             material: material.clone(),
             vertices : [v1, v2, v3],
             aabb: aabb,
             centroid: centroid,
+            area: area,
         }
     }
+
+    // Copy placed by `matrix`, normals follow its inverse transpose
+    pub fn transformed(&self, matrix: &Mat4) -> Self {
+        let normal_matrix = Mat3A::from_mat4(*matrix).inverse().transpose();
+        let vertices = self.vertices.each_ref().map(|vertex| Vertex::new(
+            matrix.transform_point3a(vertex.position),
+            (normal_matrix * vertex.normal).normalize_or_zero(),
+            matrix.transform_vector3a(vertex.binormal).normalize_or_zero(),
+            matrix.transform_vector3a(vertex.tangent).normalize_or_zero(),
+            vertex.uvs.clone(),
+        ));
+
+        let [vertex1, vertex2, vertex3] = vertices;
+        Triangle::new(self.material.clone(), vertex1, vertex2, vertex3)
+    }
+
+    // Axes of the ray cone footprint `t` along the ray, mapped into every UV set of the vertices
+    fn uv_footprints(&self, ray: &Ray, t: f32) -> Vec<[Vec2; 2]> {
+        let uv_count = self.vertices[0].uvs.len();
+        let width = ray.cone.width_at(t * ray.direction.length());
+
+        let edge1 = self.vertices[1].position - self.vertices[0].position;
+        let edge2 = self.vertices[2].position - self.vertices[0].position;
+        let normal = edge1.cross(edge2).normalize_or_zero();
+
+        // Gram matrix of the edges, to express surface vectors in barycentric coordinates
+        let e11 = edge1.dot(edge1);
+        let e12 = edge1.dot(edge2);
+        let e22 = edge2.dot(edge2);
+        let det = e11 * e22 - e12 * e12;
+
+        if width <= 0.0 || normal == Vec3A::ZERO || det.abs() < Triangle::EPSILON {
+            return vec![[Vec2::ZERO; 2]; uv_count];
+        }
+
+        // The cone is stretched along the direction the ray travels over the surface
+        let direction = ray.direction.normalize();
+        let cosine = normal.dot(direction).abs().max(0.01);
+        let mut major = (direction - normal * normal.dot(direction)).normalize_or_zero();
+        if major == Vec3A::ZERO {
+            major = edge1.normalize();
+        }
+        let minor = normal.cross(major);
+        let axes = [major * (width / cosine), minor * width];
+
+        (0..uv_count).map(|index| {
+            let uv0 = self.vertices[0].uvs[index];
+            let duv1 = self.vertices[1].uvs[index] - uv0;
+            let duv2 = self.vertices[2].uvs[index] - uv0;
+
+            axes.map(|axis| {
+                let a1 = axis.dot(edge1);
+                let a2 = axis.dot(edge2);
+                let s = (a1 * e22 - a2 * e12) / det;
+                let t = (a2 * e11 - a1 * e12) / det;
+                Vec2::new(duv1.x * s + duv2.x * t, duv1.y * s + duv2.y * t)
+            })
+        }).collect()
+    }
 }
 
 impl Traceable for Triangle {
@@ -71,9 +133,10 @@ impl Traceable for Triangle {
             return (None, self);
         }
 
+        // Set numbers are kept in z, the vertices list the same sets in the same order
         let mut uvs = self.vertices[0].uvs.clone();
-        for uv in uvs.iter_mut() {
-            *uv = self.vertices[0].uvs[uv.z as usize] * (1.0 - v - u) + self.vertices[1].uvs[uv.z as usize] * u + self.vertices[2].uvs[uv.z as usize] * v;
+        for (index, uv) in uvs.iter_mut().enumerate() {
+            *uv = self.vertices[0].uvs[index] * (1.0 - v - u) + self.vertices[1].uvs[index] * u + self.vertices[2].uvs[index] * v;
         }
         
         let normal = self.vertices[0].normal * (1.0 - v - u) + self.vertices[1].normal * u + self.vertices[2].normal * v;
@@ -87,10 +150,16 @@ impl Traceable for Triangle {
             binormal: binormal.normalize(), 
             tangent: tangent.normalize(), 
             uvs: uvs, 
+            uv_footprints: Vec::new(),
             front_face: front_face,
+            primitive: 0,
         }), self);
     }
 
+    fn finalize(&self, ray: &Ray, hit_result: &mut HitResult) {
+        hit_result.uv_footprints = self.uv_footprints(ray, hit_result.t);
+    }
+
     fn pdf(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
         let (hit_result_option, _) = self.hit(ray, t_min, t_max);
         if !hit_result_option.is_some() {
@@ -98,18 +167,22 @@ impl Traceable for Triangle {
         }
         let hit_result = hit_result_option.unwrap();
 
-        let area = 0.5 * (self.vertices[0].position - self.vertices[1].position).cross(
-            self.vertices[0].position - self.vertices[2].position
-        ).length();
-        let distance_squared = hit_result.t * hit_result.t;
-        let cosine = ray.direction.dot(hit_result.normal).abs();
+        let geometric_normal = (self.vertices[1].position - self.vertices[0].position).cross(
+            self.vertices[2].position - self.vertices[0].position
+        ).normalize();
+        let distance_squared = hit_result.t * hit_result.t * ray.direction.length_squared();
+        let cosine = ray.direction.normalize().dot(geometric_normal).abs();
 
-        return distance_squared / (cosine * area);
+        return distance_squared / (cosine * self.area);
     }
 
-    fn random(&self) -> Vec3A {
-        let u: f32 = rand::thread_rng().gen_range(0.0..1.0);
-        let v: f32 = rand::thread_rng().gen_range(0.0..1.0);
+    fn random(&self, origin: Vec3A, sampler: &mut dyn SampleGenerator) -> Vec3A {
+        let (r1, r2): (f32, f32) = sampler.get_2d().into();
+
+        // Uniformly distributed barycentrics over the triangle
+        let r1_sqrt = r1.sqrt();
+        let u = r1_sqrt * (1.0 - r2);
+        let v = r1_sqrt * r2;
 
         self.vertices[0].position * (1.0 - v - u) + self.vertices[1].position * u + self.vertices[2].position * v
     }
@@ -1,18 +1,16 @@
-use crate::engine::transform;
-use crate::engine::{material::*, transform::Transform};
+use crate::engine::light::*;
+use crate::engine::math::utils::*;
+use crate::engine::onb::*;
 
-use crate::engine::geometry::traceable::*;
-use crate::engine::math::ray::*;
-use crate::engine::texture::texture2d::*;
-use crate::engine::sampler::sampler::*;
-use crate::engine::material::pdf::cook_torrance_distribution::*;
-
-use glam::{Vec2, Vec3A, Vec4};
+use glam::{Vec3A};
 
+#[derive(Clone)]
 pub struct DirectionalLight {
     pub color: Vec3A,
     pub intensity: f32,
     pub light_vector: Vec3A,
+    // Apparent diameter of the light disk in radians, zero for hard shadows
+    pub angular_diameter: f32,
 }
 
 impl DirectionalLight {
@@ -21,6 +19,59 @@ impl DirectionalLight {
             color: color,
             intensity: intensity,
             light_vector: light_vector,
+            angular_diameter: 0.0,
+        }
+    }
+
+    fn cos_theta_max(&self) -> f32 {
+        (self.angular_diameter * 0.5).cos()
+    }
+
+    fn solid_angle(&self) -> f32 {
+        2.0 * std::f32::consts::PI * (1.0 - self.cos_theta_max())
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, position: Vec3A, sampler: &mut dyn SampleGenerator) -> LightSample {
+        if self.is_delta() {
+            return LightSample {
+                direction: -self.light_vector,
+                distance: f32::MAX,
+                radiance: self.color * self.intensity,
+                pdf: 1.0,
+            };
+        }
+
+        let basis = ONB::build_from_z(-self.light_vector);
+        let direction = basis.get_position(random_in_cone(self.cos_theta_max(), sampler)).normalize();
+        let solid_angle = self.solid_angle();
+
+        LightSample {
+            direction: direction,
+            distance: f32::MAX,
+            radiance: self.color * self.intensity / solid_angle,
+            pdf: 1.0 / solid_angle,
         }
     }
-}
\ No newline at end of file
+
+    fn is_delta(&self) -> bool {
+        self.angular_diameter <= 0.0
+    }
+
+    fn pdf(&self, direction: Vec3A) -> f32 {
+        if self.is_delta() || (-self.light_vector).dot(direction) < self.cos_theta_max() {
+            return 0.0;
+        }
+
+        1.0 / self.solid_angle()
+    }
+
+    fn radiance(&self, direction: Vec3A) -> Vec3A {
+        if self.is_delta() || (-self.light_vector).dot(direction) < self.cos_theta_max() {
+            return Vec3A::ZERO;
+        }
+
+        self.color * self.intensity / self.solid_angle()
+    }
+}
@@ -1,3 +1,44 @@
 pub mod directional;
 pub mod point;
-pub mod spot;
\ No newline at end of file
+pub mod spot;
+
+use crate::engine::sampler::*;
+use glam::{Vec3A};
+
+pub struct LightSample {
+    // Normalized direction from the shaded position towards the light
+    pub direction: Vec3A,
+    pub distance: f32,
+    pub radiance: Vec3A,
+    // Solid angle density of `direction`, 1.0 for delta lights
+    pub pdf: f32,
+}
+
+pub trait Light {
+    fn sample(&self, position: Vec3A, sampler: &mut dyn SampleGenerator) -> LightSample;
+
+    // Delta lights can't be hit by scattered rays and are never weighted against them
+    fn is_delta(&self) -> bool {
+        true
+    }
+
+    fn pdf(&self, direction: Vec3A) -> f32 {
+        0.0
+    }
+
+    // Radiance arriving along a ray escaping the scene in `direction`
+    fn radiance(&self, direction: Vec3A) -> Vec3A {
+        Vec3A::ZERO
+    }
+}
+
+// Recommended KHR_lights_punctual range window, smoothly reaching zero at `range`
+pub fn range_attenuation(distance: f32, range: Option<f32>) -> f32 {
+    match range {
+        Some(range) if range > 0.0 => {
+            let ratio = distance / range;
+            (1.0 - ratio.powi(4)).clamp(0.0, 1.0).powi(2)
+        },
+        _ => 1.0,
+    }
+}
@@ -0,0 +1,39 @@
+use crate::engine::light::*;
+
+use glam::{Vec3A};
+
+#[derive(Clone)]
+pub struct PointLight {
+    pub color: Vec3A,
+    pub intensity: f32,
+    pub position: Vec3A,
+    pub range: Option<f32>,
+}
+
+impl PointLight {
+    pub fn new(color: Vec3A, intensity: f32, position: Vec3A, range: Option<f32>) -> Self {
+        Self {
+            color: color,
+            intensity: intensity,
+            position: position,
+            range: range,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, position: Vec3A, sampler: &mut dyn SampleGenerator) -> LightSample {
+        let to_light = self.position - position;
+        let distance_squared = to_light.length_squared().max(1e-8);
+        let distance = distance_squared.sqrt();
+
+        let attenuation = range_attenuation(distance, self.range) / distance_squared;
+
+        LightSample {
+            direction: to_light / distance,
+            distance: distance,
+            radiance: self.color * self.intensity * attenuation,
+            pdf: 1.0,
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::engine::light::*;
+
+use glam::{Vec3A};
+
+#[derive(Clone)]
+pub struct SpotLight {
+    pub color: Vec3A,
+    pub intensity: f32,
+    pub position: Vec3A,
+    // Direction the spot is pointing to
+    pub light_vector: Vec3A,
+    pub range: Option<f32>,
+    pub cos_inner_cone_angle: f32,
+    pub cos_outer_cone_angle: f32,
+}
+
+impl SpotLight {
+    pub fn new(color: Vec3A, intensity: f32, position: Vec3A, light_vector: Vec3A,
+        range: Option<f32>, inner_cone_angle: f32, outer_cone_angle: f32) -> Self {
+        Self {
+            color: color,
+            intensity: intensity,
+            position: position,
+            light_vector: light_vector,
+            range: range,
+            cos_inner_cone_angle: inner_cone_angle.cos(),
+            cos_outer_cone_angle: outer_cone_angle.cos(),
+        }
+    }
+
+    fn cone_attenuation(&self, direction: Vec3A) -> f32 {
+        let cos_angle = self.light_vector.dot(direction);
+        if self.cos_inner_cone_angle <= self.cos_outer_cone_angle {
+            return if cos_angle >= self.cos_outer_cone_angle {1.0} else {0.0};
+        }
+
+        let t = ((cos_angle - self.cos_outer_cone_angle) /
+            (self.cos_inner_cone_angle - self.cos_outer_cone_angle)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, position: Vec3A, sampler: &mut dyn SampleGenerator) -> LightSample {
+        let to_light = self.position - position;
+        let distance_squared = to_light.length_squared().max(1e-8);
+        let distance = distance_squared.sqrt();
+        let direction = to_light / distance;
+
+        let attenuation = range_attenuation(distance, self.range) / distance_squared *
+            self.cone_attenuation(-direction);
+
+        LightSample {
+            direction: direction,
+            distance: distance,
+            radiance: self.color * self.intensity * attenuation,
+            pdf: 1.0,
+        }
+    }
+}
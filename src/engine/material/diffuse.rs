@@ -12,15 +12,20 @@ pub struct DiffuseMaterial {
 }
 
 impl Material for DiffuseMaterial {
-    fn scatter(&self, ray: &Ray, hit_result : &HitResult) -> ScatterResult {
+    fn scatter(&self, ray: &Ray, hit_result : &HitResult, sampler: &mut dyn SampleGenerator) -> ScatterResult {
         ScatterResult{
             attenuation: Vec3A::ONE, 
             scatter: Some(Rc::new(CosinePDF::new(hit_result.normal))),
             alpha_masked: false,
+            specular: false,
             hit_result: hit_result.clone()
         }
     }
 
+    fn bsdf(&self, ray: &Ray, hit_result : &HitResult, direction: Vec3A) -> Vec3A {
+        Vec3A::splat(hit_result.normal.dot(direction).max(0.0) / std::f32::consts::PI)
+    }
+
     fn emit(&self, ray: &Ray, hit_result : &HitResult) -> Vec3A {
         Vec3A::ZERO
     }
@@ -13,15 +13,20 @@ pub struct DiffuseLightMaterial {
 }
 
 impl Material for DiffuseLightMaterial {
-    fn scatter(&self, ray: &Ray, hit_result : &HitResult) -> ScatterResult {
+    fn scatter(&self, ray: &Ray, hit_result : &HitResult, sampler: &mut dyn SampleGenerator) -> ScatterResult {
         ScatterResult{
             attenuation: Vec3A::ONE, 
             scatter: None,
             alpha_masked: false,
+            specular: false,
             hit_result: hit_result.clone()
         }
     }
 
+    fn bsdf(&self, ray: &Ray, hit_result : &HitResult, direction: Vec3A) -> Vec3A {
+        Vec3A::ZERO
+    }
+
     fn emit(&self, ray: &Ray, hit_result : &HitResult) -> Vec3A {
         self.color
     }
@@ -16,17 +16,22 @@ pub fn reflect(eye: Vec3A, normal: Vec3A) -> Vec3A {
 }
 
 impl Material for MetalMaterial {
-    fn scatter(&self, ray: &Ray, hit_result : &HitResult) -> ScatterResult {
-        let direction = reflect(ray.direction, hit_result.normal) + (1.0 - self.metalness) * random_in_unit_sphere();
+    fn scatter(&self, ray: &Ray, hit_result : &HitResult, sampler: &mut dyn SampleGenerator) -> ScatterResult {
+        let direction = reflect(ray.direction, hit_result.normal) + (1.0 - self.metalness) * random_in_unit_sphere(sampler);
         
         ScatterResult{
             attenuation: Vec3A::ONE, 
             scatter: Some(Rc::new(CosinePDF::new(direction.normalize()))),
             alpha_masked: false,
+            specular: true,
             hit_result: hit_result.clone()
         }
     }
 
+    fn bsdf(&self, ray: &Ray, hit_result : &HitResult, direction: Vec3A) -> Vec3A {
+        Vec3A::ZERO
+    }
+
     fn emit(&self, ray: &Ray, hit_result : &HitResult) -> Vec3A {
         Vec3A::ZERO
     }
@@ -1,8 +1,6 @@
 pub mod diffuse;
 pub mod diffuse_light;
 pub mod metal;
-pub mod normal;
-pub mod uv;
 pub mod refraction;
 pub mod pbr;
 pub mod pbr_metallic_roughness;
@@ -12,19 +10,35 @@ pub mod pdf;
 use crate::engine::math::ray::*;
 use glam::{Vec3A};
 use crate::engine::geometry::traceable::*;
+use crate::engine::sampler::*;
 
 use std::{sync::{Arc}, rc::Rc};
 
 use self::{pdf::PDF, diffuse::DiffuseMaterial};
 
 pub struct ScatterResult {
+    // Throughput weight of specular and alpha masked scattering, others are weighted by bsdf / pdf
     pub attenuation: Vec3A,
     pub scatter: Option<Rc<dyn PDF>> /* Scatter */,
     pub alpha_masked: bool,
+    // Specular scattering is followed without light sampling or MIS
+    pub specular: bool,
     pub hit_result: HitResult,
 }
 
 pub trait Material {
-    fn scatter(&self, ray: &Ray, hit_result : &HitResult) -> ScatterResult;
+    fn scatter(&self, ray: &Ray, hit_result : &HitResult, sampler: &mut dyn SampleGenerator) -> ScatterResult;
+    // BSDF multiplied by the cosine term, used for explicit light sampling
+    fn bsdf(&self, ray: &Ray, hit_result : &HitResult, direction: Vec3A) -> Vec3A;
     fn emit(&self, ray: &Ray, hit_result : &HitResult) -> Vec3A;
+
+    // Surface color written to the albedo AOV
+    fn albedo(&self, hit_result : &HitResult) -> Vec3A {
+        Vec3A::ONE
+    }
+
+    // Normal used for shading, after normal mapping
+    fn shading_normal(&self, hit_result : &HitResult) -> Vec3A {
+        hit_result.normal
+    }
 }
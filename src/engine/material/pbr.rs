@@ -38,14 +38,28 @@ pub fn reflect(eye: Vec3A, normal: Vec3A) -> Vec3A {
 }
 
 impl Material for PBRMaterial {
-    fn scatter(&self, ray: &Ray, hit_result : &HitResult) -> ScatterResult {
-        self.pbr_metallic_roughness.scatter(&ray, &hit_result)
+    fn scatter(&self, ray: &Ray, hit_result : &HitResult, sampler: &mut dyn SampleGenerator) -> ScatterResult {
+        self.pbr_metallic_roughness.scatter(&ray, &hit_result, sampler)
+    }
+
+
+    fn bsdf(&self, ray: &Ray, hit_result : &HitResult, direction: Vec3A) -> Vec3A {
+        self.pbr_metallic_roughness.bsdf(&ray, &hit_result, direction)
     }
     
     fn emit(&self, ray: &Ray, hit_result : &HitResult) -> Vec3A {
-        Vec3A::from(self.emissive_texture.sample(
+        Vec3A::from(self.emissive_texture.sample_footprint(
             &self.emissive_texture_sampler, 
-            self.emissive_texture.texture.get_uv_by_index(&hit_result.uvs)
+            self.emissive_texture.texture.get_uv_by_index(&hit_result.uvs),
+            self.emissive_texture.texture.get_footprint_by_index(&hit_result.uvs, &hit_result.uv_footprints)
         )) * self.emissive_factor
     }
+
+    fn albedo(&self, hit_result : &HitResult) -> Vec3A {
+        self.pbr_metallic_roughness.albedo(&hit_result)
+    }
+
+    fn shading_normal(&self, hit_result : &HitResult) -> Vec3A {
+        self.pbr_metallic_roughness.shading_normal(&hit_result)
+    }
 }
\ No newline at end of file
@@ -34,46 +34,47 @@ impl PBRMetallicRoughnessMaterial {
         return GP;
     }
 
-    fn ggx_distribution(cos_theta_NH: f32, alpha: f32) -> f32 {
-        let alpha2 = alpha * alpha;
-        let NH_sqr = cos_theta_NH * cos_theta_NH;
-        let den = NH_sqr * alpha2 + (1.0 - NH_sqr);
-        return alpha2 / ( std::f32::consts::PI * den * den );
-    }
-
     fn fresnel_schlick(F0: Vec3A, cos_theta: f32) -> Vec3A {
         return F0 + (1.0 - F0) * (1.0 - cos_theta).powf(5.0);
     }
 
-    fn cook_torrance_ggx_specular(&self, normal: Vec3A, light: Vec3A, view: Vec3A, 
-        albedo: Vec3A, roughness: f32, metallic: f32) -> (Vec3A, Vec3A, f32) {       
-        let light = light.normalize();
+    // Samples the material textures at the hit point: (albedo, shading normal, roughness, metallic)
+    fn surface(&self, hit_result : &HitResult) -> (Vec4, Vec3A, f32, f32) {
+        let mut albedo = Vec4::ONE;
+        albedo *= self.base_color_factor;
+        albedo = albedo * self.base_color_texture.sample_footprint(
+            &self.base_color_texture_sampler, 
+            self.base_color_texture.texture.get_uv_by_index(&hit_result.uvs),
+            self.base_color_texture.texture.get_footprint_by_index(&hit_result.uvs, &hit_result.uv_footprints)
+        );
 
-        let roughness_sqr = roughness * roughness;
+        let mut normal = hit_result.normal;
 
-        let H = (view + light).normalize();
+        if self.normal_texture.valid() {
+            let mut normal_map = Vec3A::from(self.normal_texture.sample_footprint(
+                &self.normal_texture_sampler, 
+                self.normal_texture.texture.get_uv_by_index(&hit_result.uvs),
+                self.normal_texture.texture.get_footprint_by_index(&hit_result.uvs, &hit_result.uv_footprints)
+            ));
+            normal_map = normal_map * 2.0 - Vec3A::ONE;
 
-        let normal_light_cos = normal.dot(light);
-        if normal_light_cos <= 0.0 {
-            return (Vec3A::ZERO, Vec3A::ZERO, 1.0);
-        }
-        let normal_view_cos = normal.dot(view);
-        if normal_view_cos <= 0.0 {
-            return (Vec3A::ZERO, Vec3A::ZERO, 1.0);
-        }
-        let normal_h_cos = normal.dot(H);
-        let h_view_cos = H.dot(view);
+            normal = normal * normal_map.z + 
+                hit_result.tangent * normal_map.x + 
+                hit_result.binormal * normal_map.y;
 
-        let mut f0 = Vec3A::new(0.04, 0.04, 0.04);
-        f0 = (1.0 - metallic) * f0 + metallic * Vec3A::from(albedo);
+            normal = normal.normalize();
+        }  
 
-        let G = Self::ggx_partial_geometry(normal_view_cos, roughness_sqr) *
-            Self::ggx_partial_geometry(normal_light_cos, roughness_sqr);
-        let F = Self::fresnel_schlick(f0, h_view_cos);
-    
-        let spec_k = G * F / (normal_view_cos * normal_h_cos);
+        let metallic_roughness = self.metalic_roughness_texture.sample_footprint(
+            &self.metalic_roughness_texture_sampler, 
+            self.metalic_roughness_texture.texture.get_uv_by_index(&hit_result.uvs),
+            self.metalic_roughness_texture.texture.get_footprint_by_index(&hit_result.uvs, &hit_result.uv_footprints)
+        );
 
-        return (F, spec_k.max(Vec3A::ZERO), 1.0);
+        let roughness = metallic_roughness.y * self.roughness_factor;
+        let metallic = metallic_roughness.x * self.metalic_factor;
+
+        (albedo, normal, roughness, metallic)
     }
 
     pub fn new() -> Self {
@@ -96,72 +97,68 @@ pub fn reflect(eye: Vec3A, normal: Vec3A) -> Vec3A {
 }
 
 impl Material for PBRMetallicRoughnessMaterial {
-    fn scatter(&self, ray: &Ray, hit_result : &HitResult) -> ScatterResult {
-        let mut albedo = Vec4::ONE;
-        albedo *= self.base_color_factor;
-        albedo = albedo * self.base_color_texture.sample(
-            &self.base_color_texture_sampler, 
-            self.base_color_texture.texture.get_uv_by_index(&hit_result.uvs)
-        );
+    fn scatter(&self, ray: &Ray, hit_result : &HitResult, sampler: &mut dyn SampleGenerator) -> ScatterResult {
+        let (albedo, normal, roughness, metallic) = self.surface(hit_result);
 
-        let mut normal = hit_result.normal;
+        let alpha = (roughness * roughness).max(1e-3);
+        let specular_weight = 0.5 * (1.0 + metallic);
 
-        if self.normal_texture.valid() {
-            let mut normal_map = Vec3A::from(self.normal_texture.sample(
-                &self.normal_texture_sampler, 
-                self.normal_texture.texture.get_uv_by_index(&hit_result.uvs)
-            ));
-            normal_map = normal_map * 2.0 - Vec3A::ONE;
-
-            normal = normal + 
-                hit_result.tangent * normal_map.x + 
-                hit_result.binormal * normal_map.y;
-
-            normal = normal.normalize();
-        }  
-
-        let metallic_roughness = self.metalic_roughness_texture.sample(
-            &self.metalic_roughness_texture_sampler, 
-            self.metalic_roughness_texture.texture.get_uv_by_index(&hit_result.uvs)
-        );
-
-        let roughness = metallic_roughness.y * self.roughness_factor;
-        let metallic = metallic_roughness.x * self.metalic_factor;
-
-        let mut scatter_pdf = CookTorranceDistributionPDF::new(normal);
-
-        scatter_pdf.generated_direction = scatter_pdf.base_pdf.basis.get_position(
-            random_ggx_hemisphere_direction(roughness * roughness)
-        ).normalize();
-
-        let light = scatter_pdf.generated_direction;
-
-        let (fresnel, specular, pdf) = self.cook_torrance_ggx_specular(normal, 
-            light, -ray.direction, Vec3A::from(albedo),
-            roughness, metallic);
-
-        scatter_pdf.pdf = pdf;
-
-        let mut sample = Vec3A::from(albedo) * (1.0 - fresnel) + specular;
+        let scatter_pdf = CookTorranceDistributionPDF::new(normal, -ray.direction, alpha, specular_weight);
 
         let mut scatter_result = ScatterResult{
             attenuation: Vec3A::ONE, 
             scatter: Some(Rc::new(scatter_pdf)),
             alpha_masked: false,
+            specular: false,
             hit_result: hit_result.clone()
         };
 
         if albedo.w < 0.99 {
-            sample = Vec3A::ONE;
             scatter_result.alpha_masked = true;
         }
 
-        scatter_result.attenuation = sample;
-
         return scatter_result;
     }
 
+    fn bsdf(&self, ray: &Ray, hit_result : &HitResult, direction: Vec3A) -> Vec3A {
+        let (albedo, normal, roughness, metallic) = self.surface(hit_result);
+        if albedo.w < 0.99 {
+            return Vec3A::ZERO;
+        }
+
+        let view = -ray.direction;
+        let normal_light_cos = normal.dot(direction);
+        let normal_view_cos = normal.dot(view);
+        if normal_light_cos <= 0.0 || normal_view_cos <= 0.0 {
+            return Vec3A::ZERO;
+        }
+
+        let alpha = (roughness * roughness).max(1e-3);
+        let h = (view + direction).normalize();
+
+        let mut f0 = Vec3A::new(0.04, 0.04, 0.04);
+        f0 = (1.0 - metallic) * f0 + metallic * Vec3A::from(albedo);
+
+        let d = ggx_distribution(normal.dot(h), alpha);
+        let g = Self::ggx_partial_geometry(normal_view_cos, alpha) *
+            Self::ggx_partial_geometry(normal_light_cos, alpha);
+        let f = Self::fresnel_schlick(f0, h.dot(view).max(0.0));
+
+        let specular = d * g * f / (4.0 * normal_view_cos * normal_light_cos);
+        let diffuse = (Vec3A::ONE - f) * (1.0 - metallic) * Vec3A::from(albedo) / std::f32::consts::PI;
+
+        (diffuse + specular) * normal_light_cos
+    }
+
     fn emit(&self, ray: &Ray, hit_result : &HitResult) -> Vec3A {
         Vec3A::ZERO
     }
+
+    fn albedo(&self, hit_result : &HitResult) -> Vec3A {
+        Vec3A::from(self.surface(hit_result).0)
+    }
+
+    fn shading_normal(&self, hit_result : &HitResult) -> Vec3A {
+        self.surface(hit_result).1
+    }
 }
\ No newline at end of file
@@ -4,29 +4,62 @@ use glam::{Vec2, Vec3A, Vec4};
 
 use super::*;
 
+// Mixture of GGX reflection and cosine weighted diffuse sampling. Half vectors are drawn from the
+// full normal distribution with density D·cos(θh), not from the visible normals
 #[derive(Copy, Clone)]
 pub struct CookTorranceDistributionPDF {
     pub base_pdf: PDFBase,
-    pub generated_direction: Vec3A,
-    pub pdf: f32,
+    pub view: Vec3A,
+    pub alpha: f32,
+    // Probability of sampling the specular lobe
+    pub specular_weight: f32,
 }
 
 impl CookTorranceDistributionPDF {
-    pub fn new(forward: Vec3A) -> Self {
+    pub fn new(forward: Vec3A, view: Vec3A, alpha: f32, specular_weight: f32) -> Self {
         Self {
             base_pdf: PDFBase { basis: ONB::build_from_z(forward) },
-            generated_direction: Vec3A::ZERO,
-            pdf: 1.0
+            view: view,
+            alpha: alpha,
+            specular_weight: specular_weight,
         }
     }
+
+    fn specular_value(&self, direction: Vec3A) -> f32 {
+        let half = (self.view + direction).normalize();
+        let normal_half_cos = self.base_pdf.basis.z.dot(half);
+        let view_half_cos = self.view.dot(half);
+        if normal_half_cos <= 0.0 || view_half_cos <= 0.0 {
+            return 0.0;
+        }
+
+        ggx_distribution(normal_half_cos, self.alpha) * normal_half_cos / (4.0 * view_half_cos)
+    }
 }
 
 impl PDF for CookTorranceDistributionPDF {
     fn value(&self, direction: Vec3A) -> f32 {
-        1.0
+        let cosine = self.base_pdf.basis.z.dot(direction);
+        if cosine <= 0.0 {
+            return 0.0;
+        }
+
+        let diffuse_value = cosine / std::f32::consts::PI;
+
+        self.specular_weight * self.specular_value(direction) +
+            (1.0 - self.specular_weight) * diffuse_value
     }
 
-    fn generate(&self) -> Vec3A {
-       self.generated_direction
+    fn generate(&self, sampler: &mut dyn SampleGenerator) -> Vec3A {
+        let random: f32 = sampler.get_1d();
+
+        if random < self.specular_weight {
+            let half = self.base_pdf.basis.get_position(
+                random_ggx_hemisphere_direction(self.alpha, sampler)
+            ).normalize();
+            return (2.0 * self.view.dot(half) * half - self.view).normalize();
+        }
+
+        self.base_pdf.basis.get_position(random_hemisphere_direction(sampler)).normalize()
     }
-}
\ No newline at end of file
+}
@@ -25,8 +25,8 @@ impl PDF for CosinePDF {
         scattered_pdf
     }
 
-    fn generate(&self) -> Vec3A {
-        let scattering_direction = self.base_pdf.basis.get_position(random_hemisphere_direction()).normalize();
+    fn generate(&self, sampler: &mut dyn SampleGenerator) -> Vec3A {
+        let scattering_direction = self.base_pdf.basis.get_position(random_hemisphere_direction(sampler)).normalize();
         scattering_direction
     }
 }
\ No newline at end of file
@@ -0,0 +1,20 @@
+use std::sync::Arc;
+
+use crate::engine::environment::Environment;
+use glam::{Vec3A};
+
+use super::*;
+
+pub struct EnvironmentPDF {
+    pub environment: Arc<dyn Environment>,
+}
+
+impl PDF for EnvironmentPDF {
+    fn value(&self, direction: Vec3A) -> f32 {
+        self.environment.pdf(direction)
+    }
+
+    fn generate(&self, sampler: &mut dyn SampleGenerator) -> Vec3A {
+        self.environment.generate(sampler)
+    }
+}
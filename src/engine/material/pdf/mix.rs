@@ -2,7 +2,6 @@ use crate::engine::onb::*;
 use crate::engine::math::utils::*;
 use glam::{Vec2, Vec3A, Vec4};
 use std::{sync::{Arc}, rc::Rc};
-use rand::{Rng};
 
 use super::*;
 
@@ -22,14 +21,14 @@ impl PDF for MixPDF {
         pdf_value
     }
 
-    fn generate(&self) -> Vec3A {
-        let random: f32 = rand::thread_rng().gen_range(0.0..1.0);
+    fn generate(&self, sampler: &mut dyn SampleGenerator) -> Vec3A {
+        let random: f32 = sampler.get_1d();
 
         let mut acc_weight = 0.0;
         for (index, pdf) in self.pdfs.iter().enumerate()  {
             acc_weight += self.weights[index];
             if random <= acc_weight {
-                return pdf.generate();
+                return pdf.generate(sampler);
             }
         }
     
@@ -1,9 +1,11 @@
 pub mod cosine;
 pub mod cook_torrance_distribution;
 pub mod mix;
+pub mod environment;
 pub mod traceable;
 
 use crate::engine::onb::*;
+use crate::engine::sampler::*;
 use glam::{Vec2, Vec3A, Vec4};
 
 #[derive(Copy, Clone)]
@@ -14,5 +16,5 @@ pub struct PDFBase {
 
 pub trait PDF {
     fn value(&self, direction: Vec3A) -> f32;
-    fn generate(&self) -> Vec3A;
+    fn generate(&self, sampler: &mut dyn SampleGenerator) -> Vec3A;
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use crate::engine::math::ray::Ray;
+use crate::engine::math::ray::{Ray, RayCone};
 use crate::engine::{onb::*, geometry::traceable::Traceable};
 use crate::engine::math::utils::*;
 use glam::{Vec2, Vec3A, Vec4};
@@ -18,11 +18,11 @@ impl GeometryPDF {
 
 impl PDF for GeometryPDF {
     fn value(&self, direction: Vec3A) -> f32 {
-        let ray = Ray{origin : self.origin, direction : direction};
+        let ray = Ray{origin : self.origin, direction : direction, cone : RayCone::ZERO};
         self.geometry.pdf(&ray, 0.001, f32::MAX)
     }
 
-    fn generate(&self) -> Vec3A {
-        (self.geometry.random() - self.origin).normalize()
+    fn generate(&self, sampler: &mut dyn SampleGenerator) -> Vec3A {
+        (self.geometry.random(self.origin, sampler) - self.origin).normalize()
     }
 }
\ No newline at end of file
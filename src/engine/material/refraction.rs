@@ -40,7 +40,7 @@ fn refract(ray: &Ray, hit_result: &HitResult, ior: f32) -> Vec3A {
 }
 
 impl Material for RefractionMaterial {
-    fn scatter(&self, ray: &Ray, hit_result : &HitResult) -> ScatterResult {
+    fn scatter(&self, ray: &Ray, hit_result : &HitResult, sampler: &mut dyn SampleGenerator) -> ScatterResult {
         let ior = ior(self.refraction_type, hit_result.front_face);
 
         let direction = refract(ray, hit_result, ior).normalize();
@@ -49,10 +49,15 @@ impl Material for RefractionMaterial {
             attenuation: Vec3A::ONE, 
             scatter: Some(Rc::new(CosinePDF::new(direction))),
             alpha_masked: false,
+            specular: true,
             hit_result: hit_result.clone()
         }
     }
 
+    fn bsdf(&self, ray: &Ray, hit_result : &HitResult, direction: Vec3A) -> Vec3A {
+        Vec3A::ZERO
+    }
+
     fn emit(&self, ray: &Ray, hit_result : &HitResult) -> Vec3A {
         Vec3A::ZERO
     }
@@ -0,0 +1,124 @@
+// Piecewise-constant distributions used to importance sample tabulated functions
+
+#[derive(Clone)]
+pub struct Distribution1D {
+    pub function: Vec<f32>,
+    pub cdf: Vec<f32>,
+    pub integral: f32,
+}
+
+impl Distribution1D {
+    pub fn new(function: Vec<f32>) -> Self {
+        let count = function.len();
+        let mut cdf = vec![0.0; count + 1];
+
+        for i in 0..count {
+            cdf[i + 1] = cdf[i] + function[i].max(0.0) / count as f32;
+        }
+
+        let integral = cdf[count];
+        if integral > 0.0 {
+            for value in cdf.iter_mut() {
+                *value /= integral;
+            }
+        } else if count > 0 {
+            for (i, value) in cdf.iter_mut().enumerate() {
+                *value = i as f32 / count as f32;
+            }
+        }
+
+        Self {
+            function: function,
+            cdf: cdf,
+            integral: integral,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.function.len()
+    }
+
+    // Index of the segment containing `u` together with its probability
+    pub fn sample_discrete(&self, u: f32) -> (usize, f32) {
+        let index = self.find_segment(u);
+        (index, self.discrete_pdf(index))
+    }
+
+    pub fn discrete_pdf(&self, index: usize) -> f32 {
+        self.cdf[index + 1] - self.cdf[index]
+    }
+
+    // Position in [0, 1) distributed proportionally to the function, with its density
+    pub fn sample_continuous(&self, u: f32) -> (f32, f32, usize) {
+        let index = self.find_segment(u);
+
+        let mut offset = u - self.cdf[index];
+        let segment = self.cdf[index + 1] - self.cdf[index];
+        if segment > 0.0 {
+            offset /= segment;
+        }
+
+        let pdf = self.pdf(index);
+        let x = ((index as f32 + offset) / self.count() as f32).min(1.0 - f32::EPSILON);
+
+        (x, pdf, index)
+    }
+
+    pub fn pdf(&self, index: usize) -> f32 {
+        if self.integral > 0.0 {
+            self.function[index].max(0.0) / self.integral
+        } else {
+            1.0
+        }
+    }
+
+    fn find_segment(&self, u: f32) -> usize {
+        // Last cdf entry not greater than u
+        let index = self.cdf.partition_point(|value| *value <= u);
+        index.saturating_sub(1).min(self.count() - 1)
+    }
+}
+
+pub struct Distribution2D {
+    pub conditional: Vec<Distribution1D>,
+    pub marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    // `function` is laid out row by row, `width` values per row
+    pub fn new(function: &[f32], width: usize, height: usize) -> Self {
+        let mut conditional = Vec::with_capacity(height);
+        for y in 0..height {
+            conditional.push(Distribution1D::new(function[y * width..(y + 1) * width].to_vec()));
+        }
+
+        let marginal = Distribution1D::new(conditional.iter().map(|row| row.integral).collect());
+
+        Self {
+            conditional: conditional,
+            marginal: marginal,
+        }
+    }
+
+    // Returns the sampled (u, v) in [0, 1)^2 and its density
+    pub fn sample_continuous(&self, u1: f32, u2: f32) -> (f32, f32, f32) {
+        let (v, marginal_pdf, row) = self.marginal.sample_continuous(u2);
+        let (u, conditional_pdf, _) = self.conditional[row].sample_continuous(u1);
+
+        (u, v, marginal_pdf * conditional_pdf)
+    }
+
+    pub fn pdf(&self, u: f32, v: f32) -> f32 {
+        let width = self.conditional[0].count();
+        let height = self.marginal.count();
+
+        let x = ((u * width as f32) as usize).min(width - 1);
+        let y = ((v * height as f32) as usize).min(height - 1);
+
+        if self.marginal.integral <= 0.0 {
+            return 0.0;
+        }
+
+        self.conditional[y].function[x].max(0.0) / self.marginal.integral
+    }
+}
@@ -1,2 +1,4 @@
 pub mod ray;
-pub mod utils;
\ No newline at end of file
+pub mod utils;
+pub mod distribution;
+pub mod pcg;
\ No newline at end of file
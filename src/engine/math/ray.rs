@@ -1,9 +1,40 @@
 use glam::{Vec3A};
 
+// Cone around a ray covering the footprint of the pixel it was traced for, used to pick the
+// texture level of detail
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct RayCone {
+    // Width at the ray origin
+    pub width: f32,
+    // Angle the width grows with along the ray
+    pub spread: f32,
+}
+
+impl RayCone {
+    pub const ZERO: Self = Self {
+        width: 0.0,
+        spread: 0.0,
+    };
+
+    pub fn width_at(&self, distance: f32) -> f32 {
+        (self.width + self.spread * distance).abs()
+    }
+
+    // Cone continuing from a surface `distance` along the ray. Surface curvature is ignored,
+    // the spread is kept
+    pub fn propagate(&self, distance: f32) -> Self {
+        Self {
+            width: self.width_at(distance),
+            spread: self.spread,
+        }
+    }
+}
+
 #[derive(Copy, Clone)]
 pub struct Ray {
     pub origin: Vec3A,
-    pub direction: Vec3A
+    pub direction: Vec3A,
+    pub cone: RayCone,
 }
 
 impl Ray {
@@ -11,10 +42,11 @@ impl Ray {
         Self {
             origin: Vec3A::ZERO,
             direction: Vec3A::ZERO,
+            cone: RayCone::ZERO,
         }
     }
 
     pub fn at(self, t :f32) -> Vec3A {
         self.origin + self.direction * t
     }
-}
\ No newline at end of file
+}
@@ -1,15 +1,15 @@
+use crate::engine::sampler::*;
+
 use glam::{Vec2, Vec3A};
-use rand::{Rng};
 
-fn random(min : f32, max : f32) -> Vec3A {
-    Vec3A::new(rand::thread_rng().gen_range(min..max), 
-        rand::thread_rng().gen_range(min..max), 
-        rand::thread_rng().gen_range(min..max))
+fn random(min : f32, max : f32, sampler: &mut dyn SampleGenerator) -> Vec3A {
+    Vec3A::new(min + (max - min) * sampler.get_1d(), 
+        min + (max - min) * sampler.get_1d(), 
+        min + (max - min) * sampler.get_1d())
 }
 
-pub fn random_in_unit_sphere() -> Vec3A {
-    let r1: f32 = rand::thread_rng().gen_range(0.0..1.0);
-    let r2: f32 = rand::thread_rng().gen_range(0.0..1.0);
+pub fn random_in_unit_sphere(sampler: &mut dyn SampleGenerator) -> Vec3A {
+    let (r1, r2): (f32, f32) = sampler.get_2d().into();
 
     let phi = 2.0 * std::f32::consts::PI * r1;
     let x = phi.cos() * (r2 * (1.0 - r2)).sqrt();
@@ -19,9 +19,8 @@ pub fn random_in_unit_sphere() -> Vec3A {
     Vec3A::new(x, y, z)
 }
 
-pub fn random_hemisphere_direction() -> Vec3A {
-    let r1: f32 = rand::thread_rng().gen_range(0.0..1.0);
-    let r2: f32 = rand::thread_rng().gen_range(0.0..1.0);
+pub fn random_hemisphere_direction(sampler: &mut dyn SampleGenerator) -> Vec3A {
+    let (r1, r2): (f32, f32) = sampler.get_2d().into();
     let z = (1.0 - r2).sqrt();
 
     let phi = 2.0 * std::f32::consts::PI * r1;
@@ -31,9 +30,8 @@ pub fn random_hemisphere_direction() -> Vec3A {
     Vec3A::new(x, y, z)
 }
 
-pub fn random_ggx_hemisphere_direction(alpha: f32) -> Vec3A {
-    let r1: f32 = rand::thread_rng().gen_range(0.0..1.0);
-    let r2: f32 = rand::thread_rng().gen_range(0.0..1.0);
+pub fn random_ggx_hemisphere_direction(alpha: f32, sampler: &mut dyn SampleGenerator) -> Vec3A {
+    let (r1, r2): (f32, f32) = sampler.get_2d().into();
 
     let phi = 2.0 * std::f32::consts::PI * r1;
 
@@ -46,80 +44,49 @@ pub fn random_ggx_hemisphere_direction(alpha: f32) -> Vec3A {
     Vec3A::new(x, y, cos_thetha)
 }
 
-pub fn decode_triangle_vec3_indexed(
-    buffer : &Vec<u8>, offset: usize, stride: usize, raw_size: usize,
-    indices_buffer : &Vec<u8>, indices_offset : usize, indices_stride: usize,  indices_raw_size: usize
-) -> [Vec3A; 3] {
-    let index1 = decode_int(indices_buffer, indices_offset, indices_raw_size) as usize;
-    let index2 = decode_int(indices_buffer, indices_offset + indices_stride, indices_raw_size) as usize;
-    let index3 = decode_int(indices_buffer, indices_offset + indices_stride * 2, indices_raw_size) as usize;
-
-    let pos1 = decode_vec3(buffer, offset + index1 * stride, raw_size);
-    let pos2 = decode_vec3(buffer, offset + index2 * stride, raw_size);
-    let pos3 = decode_vec3(buffer, offset + index3 * stride, raw_size);
-
-    [pos1, pos2, pos3]
+// Trowbridge-Reitz (GGX) normal distribution function
+pub fn ggx_distribution(cos_theta_nh: f32, alpha: f32) -> f32 {
+    let alpha2 = alpha * alpha;
+    let nh_sqr = cos_theta_nh * cos_theta_nh;
+    let den = nh_sqr * alpha2 + (1.0 - nh_sqr);
+    return alpha2 / ( std::f32::consts::PI * den * den );
 }
 
-pub fn decode_triangle_vec3(buffer : &Vec<u8>, offset : usize, stride : usize, 
-    raw_size : usize) -> [Vec3A; 3] {
-    let pos1 = decode_vec3(buffer, offset, raw_size);
-    let pos2 = decode_vec3(buffer, offset + stride, raw_size);
-    let pos3 = decode_vec3(buffer, offset + stride * 2, raw_size);
-
-    [pos1, pos2, pos3]
+pub fn luminance(color: Vec3A) -> f32 {
+    color.dot(Vec3A::new(0.2126, 0.7152, 0.0722))
 }
 
-pub fn decode_triangle_vec2_indexed(
-    buffer : &Vec<u8>, offset: usize, stride: usize, raw_size: usize,
-    indices_buffer : &Vec<u8>, indices_offset : usize, indices_stride: usize,  indices_raw_size: usize
-) -> [Vec2; 3] {
-    let index1 = decode_int(indices_buffer, indices_offset, indices_raw_size) as usize;
-    let index2 = decode_int(indices_buffer, indices_offset + indices_stride, indices_raw_size) as usize;
-    let index3 = decode_int(indices_buffer, indices_offset + indices_stride * 2, indices_raw_size) as usize;
-
-    let uv1 = decode_vec2(buffer, offset + index1 * stride, raw_size);
-    let uv2 = decode_vec2(buffer, offset + index2 * stride, raw_size);
-    let uv3 = decode_vec2(buffer, offset + index3 * stride, raw_size);
+// Veach's power heuristic (beta = 2) for combining two sampling strategies
+pub fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    let pdf_sqr = pdf * pdf;
+    let other_pdf_sqr = other_pdf * other_pdf;
+    if pdf_sqr + other_pdf_sqr <= 0.0 {
+        return 0.0;
+    }
 
-    [uv1, uv2, uv3]
+    pdf_sqr / (pdf_sqr + other_pdf_sqr)
 }
 
-pub fn decode_triangle_vec2(buffer : &Vec<u8>, offset : usize, stride : usize, 
-    raw_size : usize) -> [Vec2; 3] {
-    let uv1 = decode_vec2(buffer, offset, raw_size);
-    let uv2 = decode_vec2(buffer, offset + stride, raw_size);
-    let uv3 = decode_vec2(buffer, offset + stride * 2, raw_size);
-
-    [uv1, uv2, uv3]
-}
+pub fn random_in_cone(cos_theta_max: f32, sampler: &mut dyn SampleGenerator) -> Vec3A {
+    let (r1, r2): (f32, f32) = sampler.get_2d().into();
 
-pub fn decode_vec3(buffer : &Vec<u8>, offset : usize, raw_size : usize) -> Vec3A {
-    return Vec3A::new(
-        f32::from_le_bytes(buffer[offset..offset + raw_size].try_into().expect("Invalid x")),
-        f32::from_le_bytes(buffer[offset + raw_size..offset + raw_size * 2].try_into().expect("Invalid y")),
-        f32::from_le_bytes(buffer[offset + raw_size * 2..offset + raw_size * 3].try_into().expect("Invalid z"))
-    );
-}
+    let z = 1.0 - r2 * (1.0 - cos_theta_max);
+    let sin_thetha = (1.0 - z * z).max(0.0).sqrt();
 
-pub fn decode_vec2(buffer : &Vec<u8>, offset : usize, raw_size : usize) -> Vec2 {
-    return Vec2::new(
-        f32::from_le_bytes(buffer[offset..offset + raw_size].try_into().expect("Invalid x")),
-        f32::from_le_bytes(buffer[offset + raw_size..offset + raw_size * 2].try_into().expect("Invalid y"))
-    );
-}
+    let phi = 2.0 * std::f32::consts::PI * r1;
+    let x = phi.cos() * sin_thetha;
+    let y = phi.sin() * sin_thetha;
 
-pub fn decode_scalar(buffer : &Vec<u8>, offset : usize, raw_size : usize) -> f32 {
-    return f32::from_le_bytes(buffer[offset..offset + raw_size].try_into().expect("Invalid scalar"));
+    Vec3A::new(x, y, z)
 }
 
-pub fn decode_int(buffer : &Vec<u8>, offset : usize, raw_size : usize) -> u32 {
-    if raw_size == 1 {
-        return u8::from_le_bytes(buffer[offset..offset + raw_size].try_into().expect("Invalid index")) as u32;
-    } else if raw_size == 2 {
-        return u16::from_le_bytes(buffer[offset..offset + raw_size].try_into().expect("Invalid index")) as u32;
-    } else if raw_size == 4 {
-        return u32::from_le_bytes(buffer[offset..offset + raw_size].try_into().expect("Invalid index"));
+// FNV-1a, stable across builds so it can be stored in files
+pub fn hash_bytes(hash: u64, data: &[u8]) -> u64 {
+    let mut hash = if hash == 0 {0xcbf29ce484222325} else {hash};
+    for byte in data {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
     }
-    return 0;
-}
\ No newline at end of file
+
+    hash
+}
@@ -0,0 +1,220 @@
+use crate::engine::aov::*;
+use crate::engine::tone_mapping::*;
+
+use glam::{Vec3A};
+
+use std::path::Path;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::io;
+
+use image::{ImageBuffer, Rgb};
+use image::hdr::HdrEncoder;
+
+#[derive(Debug)]
+pub enum OutputError {
+    // File that could not be created
+    Io(String, io::Error),
+    // File that could not be encoded or written by the image or EXR encoder
+    Encode(String, String),
+}
+
+impl fmt::Display for OutputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OutputError::Io(path, error) => write!(f, "Failed to create {}: {}", path, error),
+            OutputError::Encode(path, message) => write!(f, "Failed to save {}: {}", path, message),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum OutputFormat {
+    // 8 bit image in whatever format `image` picks from the extension
+    LDR,
+    // 32 bit float OpenEXR with linear radiance
+    EXR,
+    // Radiance RGBE with linear radiance
+    HDR,
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "exr" => Some(OutputFormat::EXR),
+            "hdr" => Some(OutputFormat::HDR),
+            "png" | "jpg" | "jpeg" | "bmp" | "tga" | "ldr" => Some(OutputFormat::LDR),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &str) -> Self {
+        Path::new(path)
+            .extension()
+            .and_then(OsStr::to_str)
+            .and_then(Self::from_name)
+            .unwrap_or(OutputFormat::LDR)
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        *self != OutputFormat::LDR
+    }
+}
+
+// Writes the beauty image and the AOV buffers, as layers of the same file for EXR and as
+// `<name>.<aov>.<ext>` images next to it otherwise
+pub fn save_render(path: &str, format: OutputFormat, width: u32, height: u32, pixels: &[Vec3A],
+    aovs: &[(AOV, Vec<Vec3A>)], tone_mapping: &ToneMapping) -> Result<(), OutputError> {
+    if format == OutputFormat::EXR && !aovs.is_empty() {
+        return save_layered_exr(path, width, height, pixels, aovs);
+    }
+
+    save_image(path, format, width, height, pixels, tone_mapping)?;
+
+    for (aov, aov_pixels) in aovs {
+        let aov_path = aov_path(path, *aov);
+        if format.is_hdr() {
+            save_image(aov_path.as_str(), format, width, height, aov_pixels, tone_mapping)?;
+        } else {
+            save_ldr(aov_path.as_str(), width, height, aov_pixels,
+                |value| aov.visualize(value, tone_mapping), tone_mapping)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn aov_path(path: &str, aov: AOV) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().and_then(OsStr::to_str).unwrap_or("");
+    let file_name = match path.extension().and_then(OsStr::to_str) {
+        Some(extension) => format!("{}.{}.{}", stem, aov.name(), extension),
+        None => format!("{}.{}", stem, aov.name()),
+    };
+
+    path.with_file_name(file_name).to_string_lossy().into_owned()
+}
+
+// `path` with the index of the camera rendering it before the extension
+pub fn camera_path(path: &str, camera: usize) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().and_then(OsStr::to_str).unwrap_or("");
+    let file_name = match path.extension().and_then(OsStr::to_str) {
+        Some(extension) => format!("{}.camera{}.{}", stem, camera, extension),
+        None => format!("{}.camera{}", stem, camera),
+    };
+
+    path.with_file_name(file_name).to_string_lossy().into_owned()
+}
+
+// `path` with the frame number before the extension, zero padded so frames sort in order
+pub fn frame_path(path: &str, frame: u32) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().and_then(OsStr::to_str).unwrap_or("");
+    let file_name = match path.extension().and_then(OsStr::to_str) {
+        Some(extension) => format!("{}.{:04}.{}", stem, frame, extension),
+        None => format!("{}.{:04}", stem, frame),
+    };
+
+    path.with_file_name(file_name).to_string_lossy().into_owned()
+}
+
+// Writes linear `pixels` (row major, `width` per row), tone mapped for LDR targets only
+pub fn save_image(path: &str, format: OutputFormat, width: u32, height: u32, pixels: &[Vec3A],
+    tone_mapping: &ToneMapping) -> Result<(), OutputError> {
+    match format {
+        OutputFormat::LDR => save_ldr(path, width, height, pixels, |color| tone_mapping.apply(color), tone_mapping),
+        OutputFormat::EXR => save_exr(path, width, height, pixels),
+        OutputFormat::HDR => save_hdr(path, width, height, pixels),
+    }
+}
+
+// Writes raw `values` for HDR formats, a blue to red ramp normalized by the maximum otherwise
+pub fn save_heatmap(path: &str, width: u32, height: u32, values: &[f32]) -> Result<(), OutputError> {
+    let format = OutputFormat::from_path(path);
+    let pixels: Vec<Vec3A> = values.iter().map(|value| Vec3A::splat(*value)).collect();
+    let tone_mapping = ToneMapping::new();
+
+    if format.is_hdr() {
+        return save_image(path, format, width, height, &pixels, &tone_mapping);
+    }
+
+    let max = values.iter().copied().fold(0.0, f32::max).max(1.0);
+    save_ldr(path, width, height, &pixels, |value| heat_color(value.x / max), &tone_mapping)
+}
+
+fn heat_color(t: f32) -> Vec3A {
+    const RAMP: [Vec3A; 5] = [
+        Vec3A::new(0.0, 0.0, 0.5),
+        Vec3A::new(0.0, 0.5, 1.0),
+        Vec3A::new(0.0, 1.0, 0.0),
+        Vec3A::new(1.0, 1.0, 0.0),
+        Vec3A::new(1.0, 0.0, 0.0),
+    ];
+
+    let position = t.clamp(0.0, 1.0) * (RAMP.len() - 1) as f32;
+    let index = (position as usize).min(RAMP.len() - 2);
+    RAMP[index].lerp(RAMP[index + 1], position - index as f32)
+}
+
+// `display` maps a pixel to [0, 1], `tone_mapping` only decides how it is quantized
+fn save_ldr(path: &str, width: u32, height: u32, pixels: &[Vec3A], display: impl Fn(Vec3A) -> Vec3A,
+    tone_mapping: &ToneMapping) -> Result<(), OutputError> {
+    let mut rgb_frame_buffer = ImageBuffer::new(width, height);
+
+    for (x, y, pixel) in rgb_frame_buffer.enumerate_pixels_mut() {
+        let color = display(pixels[(y * width + x) as usize]);
+        *pixel = Rgb(tone_mapping.quantize(color, x, y));
+    }
+
+    rgb_frame_buffer.save(path).map_err(|error| OutputError::Encode(path.to_string(), error.to_string()))
+}
+
+fn save_exr(path: &str, width: u32, height: u32, pixels: &[Vec3A]) -> Result<(), OutputError> {
+    use exr::prelude::*;
+
+    write_rgb_file(path, width as usize, height as usize, |x, y| {
+        let color = pixels[y * width as usize + x];
+        (color.x, color.y, color.z)
+    }).map_err(|error| OutputError::Encode(path.to_string(), error.to_string()))
+}
+
+fn save_hdr(path: &str, width: u32, height: u32, pixels: &[Vec3A]) -> Result<(), OutputError> {
+    let file = fs::File::create(path).map_err(|error| OutputError::Io(path.to_string(), error))?;
+    let rgb: Vec<Rgb<f32>> = pixels.iter().map(|color| Rgb([color.x, color.y, color.z])).collect();
+
+    HdrEncoder::new(io::BufWriter::new(file))
+        .encode(&rgb, width as usize, height as usize)
+        .map_err(|error| OutputError::Encode(path.to_string(), error.to_string()))
+}
+
+// Single part EXR with the beauty in R, G, B and every AOV in `<aov>.<channel>`
+fn save_layered_exr(path: &str, width: u32, height: u32, pixels: &[Vec3A], aovs: &[(AOV, Vec<Vec3A>)]) -> Result<(), OutputError> {
+    use exr::prelude::*;
+
+    fn channel(name: String, pixels: &[Vec3A], component: usize) -> AnyChannel<FlatSamples> {
+        AnyChannel::new(name.as_str(), FlatSamples::F32(pixels.iter().map(|pixel| pixel[component]).collect()))
+    }
+
+    let mut channels = Vec::new();
+    for (component, name) in ["R", "G", "B"].iter().enumerate() {
+        channels.push(channel(name.to_string(), pixels, component));
+    }
+
+    for (aov, aov_pixels) in aovs {
+        for (component, name) in aov.channels().iter().enumerate() {
+            channels.push(channel(format!("{}.{}", aov.name(), name), aov_pixels, component));
+        }
+    }
+
+    let layer = Layer::new(
+        (width as usize, height as usize),
+        LayerAttributes::default(),
+        Encoding::FAST_LOSSLESS,
+        AnyChannels::sort(SmallVec::from_vec(channels)),
+    );
+
+    Image::from_layer(layer).write().to_file(path)
+        .map_err(|error| OutputError::Encode(path.to_string(), error.to_string()))
+}
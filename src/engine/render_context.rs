@@ -1,4 +1,11 @@
 use crate::engine::scene::*;
+use crate::engine::camera::*;
+use crate::engine::output::*;
+use crate::engine::aov::*;
+use crate::engine::tone_mapping::*;
+use crate::engine::math::utils::*;
+use crate::engine::sampler::*;
+use std::sync::Arc;
 use workerpool::Pool;
 use workerpool::thunk::{Thunk, ThunkWorker};
 
@@ -6,11 +13,38 @@ extern crate num_cpus;
 
 pub const TILE_SIZE: usize = 32;
 
+#[derive(Clone)]
 pub struct RenderContext {
-    pub scene: Scene,
+    // Shared with the contexts of other frames, `Arc::make_mut` poses it for the next one
+    pub scene: Arc<Scene>,
     pub spp: u32,
     pub output: String,
+    // Overrides the format picked from the `output` extension
+    pub format: Option<OutputFormat>,
+    // Extra buffers written with the beauty image
+    pub aovs: Vec<AOV>,
+    pub tone_mapping: ToneMapping,
+    // Write the image in progress every this many seconds and/or progressive passes
+    pub checkpoint_seconds: Option<f32>,
+    pub checkpoint_passes: Option<u32>,
+    // Accumulation state written with every checkpoint, and the one to continue from
+    pub state: Option<String>,
+    // Pixels stop sampling once their relative error falls below the threshold. `spp` becomes the
+    // average, what converged pixels leave of it goes to noisy ones up to `adaptive_max_spp`,
+    // four times `spp` when not given
+    pub adaptive_threshold: Option<f32>,
+    pub adaptive_min_spp: u32,
+    pub adaptive_max_spp: Option<u32>,
+    // Image of the samples spent per pixel
+    pub heatmap: Option<String>,
+    pub resume: Option<String>,
     pub max_depth: u32,
+    // Every sample draws from a stream derived from the seed, its pixel and its index
+    pub seed: u64,
+    // Sequence the pixel, light and BSDF sample values are drawn from
+    pub sampler: SamplerType,
+    // Bounces before paths start being terminated by russian roulette
+    pub russian_roulette_depth: u32,
     pub resolution: u32,
     pub debug_steps: bool,
 }
@@ -18,12 +52,58 @@ pub struct RenderContext {
 impl RenderContext {
     pub fn new() -> Self {
         Self {
-            scene: Scene::new(),
+            scene: Arc::new(Scene::new()),
             spp: 100,
             output: String::from("test.png"),
+            format: None,
+            aovs: Vec::new(),
+            tone_mapping: ToneMapping::new(),
+            checkpoint_seconds: None,
+            checkpoint_passes: None,
+            state: None,
+            adaptive_threshold: None,
+            adaptive_min_spp: 16,
+            adaptive_max_spp: None,
+            heatmap: None,
+            resume: None,
             max_depth: 20,
+            seed: 0,
+            sampler: SamplerType::Independent,
+            russian_roulette_depth: 3,
             resolution: 1024,
             debug_steps: false,
         }
     }
-}
\ No newline at end of file
+
+    // Identifies renders of `camera` whose samples can be accumulated together. Sample counts,
+    // outputs and tone mapping are left out, they can change between resumes, except for the
+    // stratified sampler whose strata are laid out for `spp`
+    pub fn state_hash(&self, camera: &PerspectiveCamera, width: u32, height: u32) -> u64 {
+        let mut hash = hash_bytes(0, &self.scene.fingerprint.to_le_bytes());
+        for value in camera.camera.transform.model_matrix.to_cols_array() {
+            hash = hash_bytes(hash, &value.to_le_bytes());
+        }
+        for value in [camera.camera.width, camera.camera.height, camera.camera.focal_length] {
+            hash = hash_bytes(hash, &value.to_le_bytes());
+        }
+        hash = hash_bytes(hash, &width.to_le_bytes());
+        hash = hash_bytes(hash, &height.to_le_bytes());
+        hash = hash_bytes(hash, &self.max_depth.to_le_bytes());
+        hash = hash_bytes(hash, &self.russian_roulette_depth.to_le_bytes());
+        hash = hash_bytes(hash, &self.seed.to_le_bytes());
+        hash = hash_bytes(hash, self.sampler.name().as_bytes());
+        // Samples drawn for other strata would not be stratified against the ones accumulated
+        if self.sampler == SamplerType::Stratified {
+            hash = hash_bytes(hash, &self.spp.to_le_bytes());
+        }
+        // Every frame of an animation is a different scene
+        if self.scene.animation.is_some() {
+            hash = hash_bytes(hash, &self.scene.time.to_le_bytes());
+        }
+        for aov in self.aovs.iter() {
+            hash = hash_bytes(hash, aov.name().as_bytes());
+        }
+
+        hash
+    }
+}
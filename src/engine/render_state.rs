@@ -0,0 +1,240 @@
+use crate::engine::aov::*;
+use crate::engine::frame_buffer::*;
+use crate::engine::output::OutputError;
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+
+const MAGIC: &[u8; 8] = b"PUPSYACC";
+const VERSION: u32 = 4;
+
+#[derive(Debug)]
+pub enum RenderStateError {
+    Io(io::Error),
+    // Not a state file or a truncated one
+    Format(String),
+    // The state was written for a different scene, resolution or settings
+    Mismatch(String),
+    // The image or heat map written with the state could not be saved
+    Output(OutputError),
+}
+
+impl fmt::Display for RenderStateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RenderStateError::Io(error) => write!(f, "Render state I/O error: {}", error),
+            RenderStateError::Format(message) => write!(f, "Invalid render state: {}", message),
+            RenderStateError::Mismatch(message) => write!(f, "Render state does not match this render: {}", message),
+            RenderStateError::Output(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl From<io::Error> for RenderStateError {
+    fn from(error: io::Error) -> Self {
+        RenderStateError::Io(error)
+    }
+}
+
+impl From<OutputError> for RenderStateError {
+    fn from(error: OutputError) -> Self {
+        RenderStateError::Output(error)
+    }
+}
+
+// Everything needed to continue an interrupted progressive render
+pub struct RenderState {
+    // `RenderContext::state_hash` of the render that produced the state
+    pub hash: u64,
+    pub seed: u64,
+    // Completed progressive passes and the samples per pixel they added up to, pixels of adaptive
+    // renders can have fewer or, past `spp`, more
+    pub pass_index: u32,
+    pub samples_done: u32,
+    // Samples per pixel the pass being rendered goes up to, 0 between passes
+    pub pass_in_progress: u32,
+    pub frame_buffer: FrameBuffer,
+}
+
+impl RenderState {
+    pub fn new(hash: u64, seed: u64, frame_buffer: FrameBuffer) -> Self {
+        Self {
+            hash: hash,
+            seed: seed,
+            pass_index: 0,
+            samples_done: 0,
+            pass_in_progress: 0,
+            frame_buffer: frame_buffer,
+        }
+    }
+
+    pub fn validate(&self, hash: u64, seed: u64, width: u32, height: u32, aovs: &[AOV]) -> Result<(), RenderStateError> {
+        if self.seed != seed {
+            return Err(RenderStateError::Mismatch(format!("state was rendered with seed {}, render uses {}",
+                self.seed, seed)));
+        }
+
+        if self.frame_buffer.width != width || self.frame_buffer.height != height {
+            return Err(RenderStateError::Mismatch(format!("state is {}x{}, render is {}x{}",
+                self.frame_buffer.width, self.frame_buffer.height, width, height)));
+        }
+
+        let state_aovs: Vec<AOV> = self.frame_buffer.aovs.iter().map(|(aov, _)| *aov).collect();
+        if state_aovs != aovs {
+            return Err(RenderStateError::Mismatch(format!("state has AOVs {:?}, render has {:?}", state_aovs, aovs)));
+        }
+
+        if self.hash != hash {
+            return Err(RenderStateError::Mismatch(String::from("scene or render settings changed")));
+        }
+
+        Ok(())
+    }
+
+    // Written to a temporary file first so a render killed while saving keeps the previous state
+    pub fn save(&self, path: &str) -> Result<(), RenderStateError> {
+        let frame_buffer = &self.frame_buffer;
+
+        let mut data = Vec::new();
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&VERSION.to_le_bytes());
+        data.extend_from_slice(&self.hash.to_le_bytes());
+        data.extend_from_slice(&self.seed.to_le_bytes());
+        data.extend_from_slice(&self.pass_index.to_le_bytes());
+        data.extend_from_slice(&self.samples_done.to_le_bytes());
+        data.extend_from_slice(&self.pass_in_progress.to_le_bytes());
+        data.extend_from_slice(&frame_buffer.width.to_le_bytes());
+        data.extend_from_slice(&frame_buffer.height.to_le_bytes());
+
+        data.extend_from_slice(&(frame_buffer.aovs.len() as u32).to_le_bytes());
+        for (aov, _) in frame_buffer.aovs.iter() {
+            let name = aov.name().as_bytes();
+            data.extend_from_slice(&(name.len() as u32).to_le_bytes());
+            data.extend_from_slice(name);
+        }
+
+        for samples in frame_buffer.samples.iter() {
+            data.extend_from_slice(&samples.to_le_bytes());
+        }
+
+        for luminance_squared in frame_buffer.luminance_squared.iter() {
+            data.extend_from_slice(&luminance_squared.to_le_bytes());
+        }
+
+        for buffer in std::iter::once(&frame_buffer.color).chain(frame_buffer.aovs.iter().map(|(_, buffer)| buffer)) {
+            for value in buffer.iter() {
+                for component in value.to_array() {
+                    data.extend_from_slice(&component.to_le_bytes());
+                }
+            }
+        }
+
+        let temporary_path = format!("{}.tmp", path);
+        fs::File::create(temporary_path.as_str())?.write_all(&data)?;
+        fs::rename(temporary_path.as_str(), path)?;
+
+        Ok(())
+    }
+
+    pub fn load(path: &str) -> Result<Self, RenderStateError> {
+        let mut data = Vec::new();
+        fs::File::open(path)?.read_to_end(&mut data)?;
+
+        let mut reader = StateReader{data: &data, position: 0};
+
+        if reader.bytes(MAGIC.len())? != MAGIC {
+            return Err(RenderStateError::Format(format!("{} is not a render state file", path)));
+        }
+
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(RenderStateError::Format(format!("unsupported version {}", version)));
+        }
+
+        let hash = reader.u64()?;
+        let seed = reader.u64()?;
+        let pass_index = reader.u32()?;
+        let samples_done = reader.u32()?;
+        let pass_in_progress = reader.u32()?;
+        let width = reader.u32()?;
+        let height = reader.u32()?;
+
+        let aov_count = reader.u32()?;
+        let mut aovs = Vec::new();
+        for _ in 0..aov_count {
+            let length = reader.u32()? as usize;
+            let name = String::from_utf8_lossy(reader.bytes(length)?).into_owned();
+            aovs.push(AOV::from_name(name.as_str())
+                .ok_or(RenderStateError::Format(format!("unknown AOV {}", name)))?);
+        }
+
+        // Sample count, squared luminance and the color and AOV sums of every pixel, checked
+        // before anything is allocated for them
+        let pixel_size = 4 + 4 + 12 * (1 + aovs.len() as u64);
+        if width as u64 * height as u64 * pixel_size != reader.remaining() as u64 {
+            return Err(RenderStateError::Format(format!("pixel data does not match the {}x{} size", width, height)));
+        }
+
+        let mut frame_buffer = FrameBuffer::new(width, height, &aovs);
+
+        for samples in frame_buffer.samples.iter_mut() {
+            *samples = reader.u32()?;
+        }
+
+        for luminance_squared in frame_buffer.luminance_squared.iter_mut() {
+            *luminance_squared = reader.f32()?;
+        }
+
+        for buffer in std::iter::once(&mut frame_buffer.color).chain(frame_buffer.aovs.iter_mut().map(|(_, buffer)| buffer)) {
+            for value in buffer.iter_mut() {
+                value.x = reader.f32()?;
+                value.y = reader.f32()?;
+                value.z = reader.f32()?;
+            }
+        }
+
+        Ok(Self {
+            hash: hash,
+            seed: seed,
+            pass_index: pass_index,
+            samples_done: samples_done,
+            pass_in_progress: pass_in_progress,
+            frame_buffer: frame_buffer,
+        })
+    }
+}
+
+struct StateReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> StateReader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], RenderStateError> {
+        if self.position + count > self.data.len() {
+            return Err(RenderStateError::Format(String::from("unexpected end of file")));
+        }
+
+        let bytes = &self.data[self.position..self.position + count];
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    fn u32(&mut self) -> Result<u32, RenderStateError> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, RenderStateError> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, RenderStateError> {
+        Ok(f32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+}
@@ -9,21 +9,26 @@ use crate::engine::geometry::bvh::node::*;
 use crate::engine::material::pdf::cosine::*;
 use workerpool::Pool;
 use workerpool::thunk::{Thunk, ThunkWorker};
-use rand::Rng;
 
-use image::{ImageBuffer};
 use std::rc::Rc;
 use std::sync::{Arc};
-use image::{Rgb};
 
-use super::geometry::traceable::Traceable;
+use super::geometry::traceable::{HitResult, Traceable};
+use super::material::Material;
 use super::material::pdf::PDF;
-use super::material::pdf::mix::MixPDF;
+use super::material::pdf::environment::EnvironmentPDF;
 use super::material::pdf::traceable::GeometryPDF;
+use super::math::utils::{luminance, power_heuristic};
+use super::sampler::*;
+use super::output::*;
+use super::aov::*;
+use super::frame_buffer::*;
+use super::render_state::*;
 use super::profile::Profile;
 use super::profile::ProfileType;
 
 use std::thread::{self};
+use std::time::Instant;
 use std::sync::*;
 
 extern crate num_cpus;
@@ -33,123 +38,298 @@ pub struct Renderer {
 }
 
 impl Renderer {
-    fn gamma_correction(input: Vec3A) -> Vec3A {
-        Vec3A::new(
-            input.x.powf(1.0 / 2.2),
-            input.y.powf(1.0 / 2.2),
-            input.z.powf(1.0 / 2.2))
+    fn sample_punctual_lights(ray : &Ray, scene: &Scene, material: &Arc<dyn Material>,
+        hit_result: &HitResult, scatter_pdf: &dyn PDF, sampler: &mut dyn SampleGenerator) -> Vec3A {
+        let mut radiance = Vec3A::ZERO;
+
+        for light in scene.punctual_lights() {
+            let light_sample = light.sample(hit_result.position, sampler);
+            if light_sample.radiance == Vec3A::ZERO || light_sample.pdf <= 0.0 {
+                continue;
+            }
+
+            let bsdf = material.bsdf(ray, hit_result, light_sample.direction);
+            if bsdf == Vec3A::ZERO {
+                continue;
+            }
+
+            let shadow_ray = Ray{origin : hit_result.position, direction : light_sample.direction, cone : RayCone::ZERO};
+            let (occluder, _) = scene.bvh.hit(&shadow_ray, 0.001, light_sample.distance - 0.001);
+            if occluder.is_some() {
+                continue;
+            }
+
+            // Area-like lights can also be reached by scattered rays, so weight both strategies
+            let weight = if light.is_delta() {1.0} else {
+                power_heuristic(light_sample.pdf, scatter_pdf.value(light_sample.direction))
+            };
+
+            radiance += bsdf * light_sample.radiance * weight / light_sample.pdf;
+        }
+
+        radiance
     }
 
-    fn tone_mapping(input: Vec3A) -> Vec3A {
-       input / (Vec3A::ONE + input)
+    fn sample_area_lights(ray : &Ray, scene: &Scene, material: &Arc<dyn Material>,
+        hit_result: &HitResult, scatter_pdf: &dyn PDF, sampler: &mut dyn SampleGenerator) -> Vec3A {
+        if scene.lights.len() == 0 {
+            return Vec3A::ZERO;
+        }
+
+        let (light_index, light_weight) = scene.light_distribution.sample_discrete(sampler.get_1d());
+        let light = &scene.lights[light_index];
+
+        let light_pdf = GeometryPDF{origin: hit_result.position, geometry: light.clone()};
+        let direction = light_pdf.generate(sampler);
+        let pdf_value = light_weight * light_pdf.value(direction);
+        if pdf_value <= 0.0 {
+            return Vec3A::ZERO;
+        }
+
+        let bsdf = material.bsdf(ray, hit_result, direction);
+        if bsdf == Vec3A::ZERO {
+            return Vec3A::ZERO;
+        }
+
+        // The first surface along the shadow ray has to be the sampled light itself
+        let shadow_ray = Ray{origin : hit_result.position, direction : direction, cone : RayCone::ZERO};
+        let (light_hit_result, traceable) = scene.bvh.hit(&shadow_ray, 0.001, f32::MAX);
+        if !light_hit_result.is_some() ||
+            traceable as *const dyn Traceable as *const () != Arc::as_ptr(light) as *const () {
+            return Vec3A::ZERO;
+        }
+
+        let emission = traceable.material().emit(&shadow_ray, &light_hit_result.unwrap());
+        let weight = power_heuristic(pdf_value, scatter_pdf.value(direction));
+
+        bsdf * emission * weight / pdf_value
     }
 
-    fn sample_scene(ray : &Ray, scene: &Scene, depth : u32) -> Vec3A {
-        let mut ray = ray.clone();
-        let mut average_sample = Vec3A::ONE;
+    fn sample_environment(ray : &Ray, scene: &Scene, material: &Arc<dyn Material>,
+        hit_result: &HitResult, scatter_pdf: &dyn PDF, sampler: &mut dyn SampleGenerator) -> Vec3A {
+        if !scene.environment.importance_sampled() {
+            return Vec3A::ZERO;
+        }
+
+        let environment_pdf = EnvironmentPDF{environment: scene.environment.clone()};
+        let direction = environment_pdf.generate(sampler);
+        let pdf_value = environment_pdf.value(direction);
+        if pdf_value <= 0.0 {
+            return Vec3A::ZERO;
+        }
+
+        let bsdf = material.bsdf(ray, hit_result, direction);
+        if bsdf == Vec3A::ZERO {
+            return Vec3A::ZERO;
+        }
+
+        let shadow_ray = Ray{origin : hit_result.position, direction : direction, cone : RayCone::ZERO};
+        let (occluder, _) = scene.bvh.hit(&shadow_ray, 0.001, f32::MAX);
+        if occluder.is_some() {
+            return Vec3A::ZERO;
+        }
 
-        for _ in 0..depth {
+        let weight = power_heuristic(pdf_value, scatter_pdf.value(direction));
+
+        bsdf * scene.environment.radiance(direction) * weight / pdf_value
+    }
+
+    fn sample_sky(ray : &Ray, scene: &Scene, scatter_pdf_value: Option<f32>) -> Vec3A {
+        let mut sky = scene.environment.radiance(ray.direction);
+
+        if scatter_pdf_value.is_some() && scene.environment.importance_sampled() {
+            sky *= power_heuristic(scatter_pdf_value.unwrap(), scene.environment.pdf(ray.direction));
+        }
+
+        for light in scene.punctual_lights() {
+            if light.is_delta() {
+                continue;
+            }
+
+            let light_radiance = light.radiance(ray.direction);
+            if light_radiance == Vec3A::ZERO {
+                continue;
+            }
+
+            let weight = match scatter_pdf_value {
+                Some(pdf_value) => power_heuristic(pdf_value, light.pdf(ray.direction)),
+                None => 1.0,
+            };
+
+            sky += light_radiance * weight;
+        }
+
+        sky
+    }
+
+    // Path traced estimate of the radiance arriving along `ray`. Every bounce samples the lights
+    // explicitly and continues with a BSDF sampled direction, both combined with the power heuristic.
+    // Paths longer than `russian_roulette_depth` bounces are terminated randomly by their throughput
+    pub fn sample_scene(ray : &Ray, scene: &Scene, depth : u32, russian_roulette_depth: u32, sampler: &mut dyn SampleGenerator) -> Vec3A {
+        Self::sample_scene_aov(ray, scene, depth, russian_roulette_depth, sampler).0
+    }
+
+    // `sample_scene` that also records the first hit of the path for the AOV buffers
+    pub fn sample_scene_aov(ray : &Ray, scene: &Scene, depth : u32, russian_roulette_depth: u32,
+        sampler: &mut dyn SampleGenerator) -> (Vec3A, AOVSample) {
+        let camera_position = ray.origin;
+        let mut ray = ray.clone();
+        let mut throughput = Vec3A::ONE;
+        let mut radiance = Vec3A::ZERO;
+        // BSDF density of the current ray direction, None for camera and specular rays
+        let mut scatter_pdf_value: Option<f32> = None;
+
+        let mut aov = AOVSample::new();
+        // Non alpha masked surfaces hit so far
+        let mut surfaces = 0;
+        // Radiance gathered up to the emission seen by the first bounce, the rest is indirect
+        let mut direct: Option<Vec3A> = None;
+
+        for bounce in 0..depth {
             let (hit_result_option, traceable) = scene.bvh.hit(&ray, 0.001, f32::MAX);
 
             if !hit_result_option.is_some() {
-                let t = 0.5 * (ray.direction.y + 1.0);
-                let sky = (1.0 - t) * Vec3A::new(1.0, 1.0, 1.0) + t * Vec3A::new(0.9, 0.3, 0.3);
+                let sky = Self::sample_sky(&ray, scene, scatter_pdf_value);
 
-                return average_sample * (sky);
+                radiance += throughput * sky;
+                break;
             }
 
-            let hit_result = &hit_result_option.unwrap();
+            let mut hit_result = hit_result_option.unwrap();
+            traceable.finalize(&ray, &mut hit_result);
+            let hit_result = &hit_result;
+            let material = traceable.material();
 
-            let mut pdfs: Vec<Rc::<dyn PDF>> = Vec::new();
-            let mut weights = Vec::new();
+            let scatter_result = material.scatter(&ray, &hit_result, sampler);
 
-            let uniform_weight = 1.0 / scene.lights.len() as f32;
+            if !scatter_result.alpha_masked {
+                surfaces += 1;
+            }
 
-            for light in scene.lights.iter() {
-                pdfs.push(Rc::new(GeometryPDF{origin: hit_result.position, geometry: light.clone()}));
-                weights.push(uniform_weight);
+            if surfaces == 1 && !scatter_result.alpha_masked {
+                let hit_result = &scatter_result.hit_result;
+                aov.albedo = material.albedo(hit_result);
+                aov.normal = material.shading_normal(hit_result);
+                aov.depth = (hit_result.position - camera_position).length();
+                aov.position = hit_result.position;
+                aov.uv = hit_result.uvs.first().copied().unwrap_or(Vec3A::ZERO) * Vec3A::new(1.0, 1.0, 0.0);
+                aov.material_id = scene.material_id(material).map_or(0, |id| id as u32 + 1);
+                aov.object_id = scene.object_id(traceable).map_or(0, |id| id as u32 + 1);
             }
 
-            let light_pdf = Rc::new(MixPDF{ 
-                pdfs: pdfs,
-                weights: weights});
+            let emmission = material.emit(&ray, &scatter_result.hit_result);
+            if emmission != Vec3A::ZERO {
+                let weight = match scatter_pdf_value {
+                    Some(pdf_value) => power_heuristic(pdf_value, scene.light_pdf(traceable, &ray)),
+                    None => 1.0,
+                };
 
-            let scatter_result = traceable.material().scatter(&ray, &hit_result);
+                radiance += throughput * emmission * weight;
+            }
 
-            let emmission = traceable.material().emit(&ray, &scatter_result.hit_result);
-            let mut sample = scatter_result.attenuation;
+            if surfaces == 2 {
+                direct.get_or_insert(radiance);
+            }
 
-            if scatter_result.scatter.is_some() {
-                let pdf = &scatter_result.scatter.unwrap();
+            if !scatter_result.scatter.is_some() {
+                break;
+            }
 
-                let mut scatter = Vec3A::ZERO;
-                let mut pdf_value = 0.0;
+            let scatter_pdf = scatter_result.scatter.clone().unwrap();
+            let position = scatter_result.hit_result.position;
+            let cone = ray.cone.propagate(hit_result.t * ray.direction.length());
 
-                if scene.lights.len() > 0 {
-                    let final_pdf = MixPDF{ 
-                        pdfs: vec![pdf.clone(), light_pdf.clone()],
-                        weights: vec![0.5, 0.5]};
+            if scatter_result.alpha_masked {
+                throughput *= scatter_result.attenuation;
+                ray = Ray{origin : position, direction : ray.direction, cone : cone};
+                continue;
+            }
 
-                    scatter = final_pdf.generate();
-                    pdf_value = final_pdf.value(scatter);
-                }
-                else {
-                    scatter = pdf.generate();
-                    pdf_value = pdf.value(scatter);
-                }
+            if scatter_result.specular {
+                throughput *= scatter_result.attenuation;
+                scatter_pdf_value = None;
+                ray = Ray{origin : position, direction : scatter_pdf.generate(sampler), cone : cone};
+                continue;
+            }
 
-                let mut scattering_ray = Ray{origin : scatter_result.hit_result.position, direction : scatter};
+            radiance += throughput * (
+                Self::sample_punctual_lights(&ray, scene, material, &scatter_result.hit_result, scatter_pdf.as_ref(), sampler) +
+                Self::sample_area_lights(&ray, scene, material, &scatter_result.hit_result, scatter_pdf.as_ref(), sampler) +
+                Self::sample_environment(&ray, scene, material, &scatter_result.hit_result, scatter_pdf.as_ref(), sampler)
+            );
 
-                if scatter_result.alpha_masked {
-                    scattering_ray = Ray{origin : scatter_result.hit_result.position, direction : ray.direction};
-                    pdf_value = 1.0;
-                }
+            let scatter = scatter_pdf.generate(sampler);
+            let pdf_value = scatter_pdf.value(scatter);
+            if pdf_value <= 0.0 {
+                break;
+            }
 
-                sample = sample / pdf_value;
+            throughput *= material.bsdf(&ray, &scatter_result.hit_result, scatter) / pdf_value;
+            if throughput == Vec3A::ZERO {
+                break;
+            }
 
-                ray = scattering_ray;
-            } else {
-                return average_sample * emmission;
+            if bounce >= russian_roulette_depth {
+                let survival_probability = throughput.max_element().min(0.95);
+                if sampler.get_1d() >= survival_probability {
+                    break;
+                }
+
+                throughput /= survival_probability;
             }
 
-            average_sample = emmission + average_sample * sample;
+            scatter_pdf_value = Some(pdf_value);
+            ray = Ray{origin : position, direction : scatter, cone : cone};
         }
 
-        //sample
-        Vec3A::ZERO
-        //average_sample
+        aov.direct = direct.unwrap_or(radiance);
+        aov.indirect = radiance - aov.direct;
+
+        (radiance, aov)
     }
 
-    pub fn render(&self, camera: Arc<PerspectiveCamera>, render_context : Arc<RenderContext>) {
+    pub fn render(&self, camera: Arc<PerspectiveCamera>, render_context : Arc<RenderContext>) -> Result<(), RenderStateError> {
         let render_time = Profile::new(format!("Render").as_str(), ProfileType::INSTANT);
 
         let height: u32 = render_context.resolution;
         let width: u32 = (height as f32 * camera.aspect_ratio()) as u32;
     
         struct WorkerInput {
-            pub task_index: usize,
             pub x: usize,
             pub y: usize,
             pub height: u32,
             pub width: u32,
+            // Samples every active pixel has once the pass is done, pixels that already have them
+            // from an interrupted pass are skipped
+            pub pass_target: u32,
+            // Per-pixel flags of the whole image, None when every pixel is sampled
+            pub active: Option<Arc<Vec<bool>>>,
+            // Samples every pixel of the image already has, the index of its first sample in this pass
+            pub sample_offsets: Arc<Vec<u32>>,
             pub render_context: Arc<RenderContext>,
             pub camera: Arc<PerspectiveCamera>
         }
 
-        #[derive(Clone)]
-        #[derive(Copy)]
         struct WorkerOutput {
-            pub task_index: usize,
+            pub x: usize,
+            pub y: usize,
+            // Sums of the pass samples
             pub tile: [[Vec3A; TILE_SIZE]; TILE_SIZE],
-            pub sample_index: u32,
+            // One tile per entry of `RenderContext::aovs`
+            pub aov_tiles: Vec<[[Vec3A; TILE_SIZE]; TILE_SIZE]>,
+            pub luminance_squared: [[f32; TILE_SIZE]; TILE_SIZE],
+            pub sample_counts: [[u32; TILE_SIZE]; TILE_SIZE],
         }
 
         impl WorkerOutput {
             pub fn new() -> Self {
                 WorkerOutput{
-                    task_index: 0,
+                    x: 0,
+                    y: 0,
                     tile: [[Vec3A::ZERO; TILE_SIZE]; TILE_SIZE], 
-                    sample_index: 0
+                    aov_tiles: Vec::new(),
+                    luminance_squared: [[0.0; TILE_SIZE]; TILE_SIZE],
+                    sample_counts: [[0; TILE_SIZE]; TILE_SIZE],
                 }
             }
         }
@@ -171,7 +351,12 @@ impl Renderer {
             fn execute(&mut self, inp: Self::Input) -> Self::Output {
                 const CACHE_LOCALITY_TILE_SIZE: usize = 4;
 
+                let aovs = &inp.render_context.aovs;
+
+                let mut sampler = inp.render_context.sampler.create(inp.render_context.seed, inp.render_context.spp);
+
                 let mut output = WorkerOutput::new();
+                output.aov_tiles = vec![[[Vec3A::ZERO; TILE_SIZE]; TILE_SIZE]; aovs.len()];
 
                 for local_y in 0..TILE_SIZE  / CACHE_LOCALITY_TILE_SIZE {
                     if inp.y + local_y * CACHE_LOCALITY_TILE_SIZE >= inp.height as usize {
@@ -190,18 +375,34 @@ impl Renderer {
                                     break;
                                 }
 
-                                let mut current_color = Vec3A::ZERO;
-                                for sample_index in 0..inp.render_context.spp {
-                                    let x = inp.x + local_x * CACHE_LOCALITY_TILE_SIZE + cache_locality_x;
-                                    let y = inp.y + local_y * CACHE_LOCALITY_TILE_SIZE + cache_locality_y;
+                                let x = inp.x + local_x * CACHE_LOCALITY_TILE_SIZE + cache_locality_x;
+                                let y = inp.y + local_y * CACHE_LOCALITY_TILE_SIZE + cache_locality_y;
+                                if x >= inp.width as usize || y >= inp.height as usize {
+                                    continue;
+                                }
 
-                                    let u = (x as f32 + rand::thread_rng().gen_range(0.0..1.0)) / (inp.width - 1) as f32;
-                                    let v = (y as f32 + rand::thread_rng().gen_range(0.0..1.0)) / (inp.height - 1) as f32;
+                                let sample_offset = inp.sample_offsets[y * inp.width as usize + x];
+                                let sample_count = match &inp.active {
+                                    Some(active) if !active[y * inp.width as usize + x] => 0,
+                                    _ => inp.pass_target.saturating_sub(sample_offset),
+                                };
+
+                                let mut current_color = Vec3A::ZERO;
+                                let mut current_aovs = vec![Vec3A::ZERO; aovs.len()];
+                                let mut current_luminance_squared = 0.0;
+                                for sample_index in 0..sample_count {
+                                    sampler.start_sample(x as u32, y as u32, sample_offset + sample_index);
+
+                                    let jitter = sampler.get_2d();
+                                    let u = (x as f32 + jitter.x) / (inp.width - 1) as f32;
+                                    let v = (y as f32 + jitter.y) / (inp.height - 1) as f32;
                     
-                                    let ray = inp.camera.get_ray(u, 1.0 - v);
+                                    let mut ray = inp.camera.get_ray(u, 1.0 - v);
+                                    ray.cone.spread = inp.camera.pixel_spread(inp.height);
                     
-                                    let mut current_sample = Renderer::sample_scene(&ray, 
-                                        &inp.render_context.scene, inp.render_context.max_depth);
+                                    let (mut current_sample, aov_sample) = Renderer::sample_scene_aov(&ray, 
+                                        &inp.render_context.scene, inp.render_context.max_depth,
+                                        inp.render_context.russian_roulette_depth, sampler.as_mut());
                 
                                     if current_sample.x.is_nan() {
                                         current_sample.x = 1.0;
@@ -213,16 +414,33 @@ impl Renderer {
                                         current_sample.z = 1.0;
                                     }
 
-                                    current_color += current_sample / inp.render_context.spp as f32;
+                                    current_color += current_sample;
+                                    current_luminance_squared += luminance(current_sample) * luminance(current_sample);
+
+                                    for (aov_index, aov) in aovs.iter().enumerate() {
+                                        if aov.filtered() {
+                                            current_aovs[aov_index] += aov_sample.get(*aov);
+                                        } else if sample_index == 0 {
+                                            current_aovs[aov_index] = aov_sample.get(*aov);
+                                        }
+                                    }
+                                }
+
+                                let tile_x = local_x * CACHE_LOCALITY_TILE_SIZE + cache_locality_x;
+                                let tile_y = local_y * CACHE_LOCALITY_TILE_SIZE + cache_locality_y;
+                                output.tile[tile_x][tile_y] = current_color;
+                                output.luminance_squared[tile_x][tile_y] = current_luminance_squared;
+                                output.sample_counts[tile_x][tile_y] = sample_count;
+                                for (aov_index, aov_color) in current_aovs.iter().enumerate() {
+                                    output.aov_tiles[aov_index][tile_x][tile_y] = *aov_color;
                                 }
-                                output.tile[local_x * CACHE_LOCALITY_TILE_SIZE + cache_locality_x][local_y * CACHE_LOCALITY_TILE_SIZE + cache_locality_y] = current_color;
                             }
                         }
                     }
                 }
 
-                output.sample_index = inp.render_context.spp - 1;
-                output.task_index = inp.task_index;
+                output.x = inp.x;
+                output.y = inp.y;
                 output
             }
         }
@@ -236,58 +454,151 @@ impl Renderer {
 
         let num_tasks = tile_x * tile_y;
 
-        let mut output_frame_buffer: Vec<WorkerOutput> = vec![WorkerOutput::new(); num_tasks];
+        let format = render_context.format
+            .unwrap_or(OutputFormat::from_path(render_context.output.as_str()));
+
+        let state_hash = render_context.state_hash(&camera, width, height);
+        let mut state = match &render_context.resume {
+            Some(path) => {
+                let state = RenderState::load(path.as_str())?;
+                state.validate(state_hash, render_context.seed, width, height, &render_context.aovs)?;
+                state
+            },
+            None => RenderState::new(state_hash, render_context.seed,
+                FrameBuffer::new(width, height, &render_context.aovs)),
+        };
 
         let (tx, rx) =  mpsc::channel();
 
-        for tile_x_index in 0..tile_x
-        {
-            for tile_y_index in 0..tile_y
+        let mut last_checkpoint = Instant::now();
+        let mut passes_since_checkpoint = 0;
+
+        // Adaptive renders spend a budget of `spp` samples per pixel on the pixels that are still
+        // noisy, up to `adaptive_max_spp` each
+        let max_spp = match render_context.adaptive_threshold {
+            Some(_) => render_context.adaptive_max_spp.unwrap_or(render_context.spp * 4).max(render_context.spp),
+            None => render_context.spp,
+        };
+        let budget = render_context.spp as u64 * width as u64 * height as u64;
+
+        for pass_target in Self::progressive_passes(max_spp) {
+            if pass_target <= state.samples_done {
+                continue;
+            }
+
+            let sample_offsets = Arc::new(state.frame_buffer.samples.clone());
+            let (active, target) = match render_context.adaptive_threshold {
+                Some(threshold) => {
+                    let active = state.frame_buffer.unconverged(threshold, render_context.adaptive_min_spp);
+                    // A pass interrupted by a checkpoint is finished with the target it started with
+                    let target = if state.pass_in_progress > 0 {state.pass_in_progress} else {
+                        state.frame_buffer.budget_target(&active, pass_target, budget)
+                    };
+                    if state.frame_buffer.pass_cost(&active, target) == 0 {
+                        break;
+                    }
+                    (Some(Arc::new(active)), target)
+                },
+                None => (None, pass_target),
+            };
+            state.pass_in_progress = target;
+
+            for tile_x_index in 0..tile_x
             {
-                let inp = WorkerInput{
-                    task_index: tile_x_index + tile_y_index * tile_x,
-                    x: tile_x_index * TILE_SIZE,
-                    y: tile_y_index * TILE_SIZE,
-                    height: height,
-                    width: width,
-                    render_context: render_context.clone(),
-                    camera: camera.clone(),
-                };
-                pool.execute_to(tx.clone(), inp);
+                for tile_y_index in 0..tile_y
+                {
+                    let inp = WorkerInput{
+                        x: tile_x_index * TILE_SIZE,
+                        y: tile_y_index * TILE_SIZE,
+                        height: height,
+                        width: width,
+                        pass_target: target,
+                        active: active.clone(),
+                        sample_offsets: sample_offsets.clone(),
+                        render_context: render_context.clone(),
+                        camera: camera.clone(),
+                    };
+                    pool.execute_to(tx.clone(), inp);
+                }
+            }
+
+            for output in rx.iter().take(num_tasks){
+                for local_x in 0..TILE_SIZE {
+                    for local_y in 0..TILE_SIZE {
+                        let aovs: Vec<Vec3A> = output.aov_tiles.iter().map(|tile| tile[local_x][local_y]).collect();
+                        state.frame_buffer.add((output.x + local_x) as u32, (output.y + local_y) as u32,
+                            output.tile[local_x][local_y], &aovs, output.luminance_squared[local_x][local_y],
+                            output.sample_counts[local_x][local_y]);
+                    }
+                }
+
+                if let Some(seconds) = render_context.checkpoint_seconds {
+                    if last_checkpoint.elapsed().as_secs_f32() >= seconds {
+                        Self::checkpoint(&render_context, &state, format)?;
+                        last_checkpoint = Instant::now();
+                        passes_since_checkpoint = 0;
+                    }
+                }
+            }
+
+            state.samples_done = pass_target;
+            state.pass_in_progress = 0;
+            state.pass_index += 1;
+            passes_since_checkpoint += 1;
+
+            // The last pass is saved with the final image
+            if state.samples_done >= max_spp {
+                break;
             }
-        }
 
-        for output in rx.iter().take(num_tasks){
-            output_frame_buffer[output.task_index] = output;
+            let checkpoint_due = render_context.checkpoint_passes
+                .map_or(false, |passes| passes_since_checkpoint >= passes);
+            if checkpoint_due {
+                Self::checkpoint(&render_context, &state, format)?;
+                last_checkpoint = Instant::now();
+                passes_since_checkpoint = 0;
+            } else if let Some(path) = &render_context.state {
+                // Every pass boundary can be resumed from, even without checkpoint images
+                state.save(path.as_str())?;
+            }
         }
 
         drop(render_time);
 
-        let png_time = Profile::new(format!("PNG").as_str(), ProfileType::INSTANT);
-        
-        let mut rgb_frame_buffer = ImageBuffer::new(width, height);
+        let save_time = Profile::new(format!("Save").as_str(), ProfileType::INSTANT);
 
-        for (x, y, pixel) in rgb_frame_buffer.enumerate_pixels_mut() {
-            *pixel = Rgb([0, 0, 0]);
-            let tile_x_index: usize = (x / TILE_SIZE as u32) as usize;
-            let tile_y_index: usize = (y / TILE_SIZE as u32) as usize;
-            let local_tile_x_index: usize = (x % TILE_SIZE as u32) as usize;
-            let local_tile_y_index: usize = (y % TILE_SIZE as u32) as usize;
+        Self::checkpoint(&render_context, &state, format)?;
 
-            let chunk = &output_frame_buffer[tile_y_index * tile_x + tile_x_index];
+        drop(save_time);
 
-            let mut scene_color = chunk.tile[local_tile_x_index][local_tile_y_index];
-    
-            //scene_color = Self::tone_mapping(scene_color);
-            scene_color *= render_context.spp as f32 / (chunk.sample_index as f32 + 1.0);
-            scene_color = Self::gamma_correction(scene_color);  
-            scene_color *= 255.0;
+        Ok(())
+    }
+
+    // Writes the image in progress and, when enabled, the state needed to resume from it
+    fn checkpoint(render_context: &RenderContext, state: &RenderState, format: OutputFormat) -> Result<(), RenderStateError> {
+        state.frame_buffer.save(render_context.output.as_str(), format, &render_context.tone_mapping)?;
 
-            *pixel = Rgb([scene_color.x as u8, scene_color.y as u8, scene_color.z as u8]);
+        if let Some(path) = &render_context.heatmap {
+            state.frame_buffer.save_heatmap(path.as_str())?;
         }
-    
-        rgb_frame_buffer.save(render_context.output.as_str()).unwrap();
 
-        drop(png_time);
+        if let Some(path) = &render_context.state {
+            state.save(path.as_str())?;
+        }
+
+        Ok(())
+    }
+
+    // Cumulative sample counts after every progressive pass: 1, 4, 16, ... up to `spp`
+    fn progressive_passes(spp: u32) -> Vec<u32> {
+        let mut passes = Vec::new();
+        let mut target = 1;
+        while target < spp {
+            passes.push(target);
+            target *= 4;
+        }
+        passes.push(spp);
+
+        passes
     }
 }
\ No newline at end of file
@@ -0,0 +1,81 @@
+use crate::engine::math::pcg::*;
+use crate::engine::sampler::*;
+
+use glam::{Vec2};
+
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53,
+    59, 61, 67, 71, 73, 79, 83, 89, 97, 101, 103, 107, 109, 113, 127, 131,
+];
+
+// Halton sequence with one prime base per dimension, randomized per pixel by a Cranley-Patterson
+// rotation. Dimensions past the prime table fall back to independent values
+pub struct HaltonSampler {
+    seed: u64,
+    rng: Pcg32,
+    x: u32,
+    y: u32,
+    sample_index: u32,
+    dimension: u32,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed: seed,
+            rng: Pcg32::new(seed, 0),
+            x: 0,
+            y: 0,
+            sample_index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn next(&mut self) -> f32 {
+        let dimension = self.dimension;
+        self.dimension += 1;
+
+        if dimension as usize >= PRIMES.len() {
+            return self.rng.next_f32();
+        }
+
+        let rotation = u32_to_unit_float(sample_hash(self.x, self.y, dimension, self.seed));
+        let value = radical_inverse(self.sample_index, PRIMES[dimension as usize]) + rotation;
+
+        (value - value.floor()).min(1.0 - f32::EPSILON)
+    }
+}
+
+impl SampleGenerator for HaltonSampler {
+    fn start_sample(&mut self, x: u32, y: u32, sample_index: u32) {
+        self.rng = Pcg32::for_sample(self.seed, x, y, sample_index);
+        self.x = x;
+        self.y = y;
+        self.sample_index = sample_index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        self.next()
+    }
+
+    fn get_2d(&mut self) -> Vec2 {
+        Vec2::new(self.next(), self.next())
+    }
+}
+
+fn radical_inverse(mut index: u32, base: u32) -> f32 {
+    let inverse_base = 1.0 / base as f64;
+    let mut inverse_base_power = 1.0;
+    let mut reversed: u64 = 0;
+
+    while index > 0 {
+        let next = index / base;
+        let digit = index - next * base;
+        reversed = reversed * base as u64 + digit as u64;
+        inverse_base_power *= inverse_base;
+        index = next;
+    }
+
+    (reversed as f64 * inverse_base_power) as f32
+}
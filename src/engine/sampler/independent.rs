@@ -0,0 +1,33 @@
+use crate::engine::math::pcg::*;
+use crate::engine::sampler::*;
+
+use glam::{Vec2};
+
+// Uniform random values with no correlation between samples
+pub struct IndependentSampler {
+    seed: u64,
+    rng: Pcg32,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed: seed,
+            rng: Pcg32::new(seed, 0),
+        }
+    }
+}
+
+impl SampleGenerator for IndependentSampler {
+    fn start_sample(&mut self, x: u32, y: u32, sample_index: u32) {
+        self.rng = Pcg32::for_sample(self.seed, x, y, sample_index);
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        self.rng.next_f32()
+    }
+
+    fn get_2d(&mut self) -> Vec2 {
+        Vec2::new(self.rng.next_f32(), self.rng.next_f32())
+    }
+}
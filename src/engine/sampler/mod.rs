@@ -1 +1,85 @@
-pub mod sampler;
\ No newline at end of file
+pub mod sampler;
+pub mod independent;
+pub mod stratified;
+pub mod halton;
+pub mod sobol;
+
+use glam::{Vec2};
+
+use self::halton::HaltonSampler;
+use self::independent::IndependentSampler;
+use self::sobol::SobolSampler;
+use self::stratified::StratifiedSampler;
+
+// Source of the sample values consumed by a path. Every call advances to the next dimension, so
+// pixel jitter, light and BSDF sampling of the same bounce line up across the samples of a pixel
+pub trait SampleGenerator {
+    // Restarts the dimensions for sample `sample_index` of pixel (x, y)
+    fn start_sample(&mut self, x: u32, y: u32, sample_index: u32);
+    fn get_1d(&mut self) -> f32;
+    fn get_2d(&mut self) -> Vec2;
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SamplerType {
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+}
+
+impl SamplerType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "independent" | "random" => Some(SamplerType::Independent),
+            "stratified" => Some(SamplerType::Stratified),
+            "halton" => Some(SamplerType::Halton),
+            "sobol" => Some(SamplerType::Sobol),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SamplerType::Independent => "independent",
+            SamplerType::Stratified => "stratified",
+            SamplerType::Halton => "halton",
+            SamplerType::Sobol => "sobol",
+        }
+    }
+
+    // `samples_per_pixel` is the sample count the strata are laid out for
+    pub fn create(&self, seed: u64, samples_per_pixel: u32) -> Box<dyn SampleGenerator> {
+        match self {
+            SamplerType::Independent => Box::new(IndependentSampler::new(seed)),
+            SamplerType::Stratified => Box::new(StratifiedSampler::new(seed, samples_per_pixel)),
+            SamplerType::Halton => Box::new(HaltonSampler::new(seed)),
+            SamplerType::Sobol => Box::new(SobolSampler::new(seed)),
+        }
+    }
+}
+
+// Hash of the pixel, dimension and seed used to decorrelate the sequences of different pixels
+pub fn sample_hash(x: u32, y: u32, dimension: u32, seed: u64) -> u32 {
+    let mut hash = (seed ^ (seed >> 32)) as u32;
+    for value in [x, y, dimension] {
+        hash ^= value.wrapping_mul(0x9E3779B9).wrapping_add(0x7F4A7C15).wrapping_add(hash << 6).wrapping_add(hash >> 2);
+        hash = mix_bits(hash);
+    }
+
+    hash
+}
+
+fn mix_bits(mut value: u32) -> u32 {
+    value ^= value >> 16;
+    value = value.wrapping_mul(0x7FEB352D);
+    value ^= value >> 15;
+    value = value.wrapping_mul(0x846CA68B);
+    value ^= value >> 16;
+    value
+}
+
+// Maps the high 24 bits to [0, 1)
+pub fn u32_to_unit_float(value: u32) -> f32 {
+    (value >> 8) as f32 * (1.0 / (1u32 << 24) as f32)
+}
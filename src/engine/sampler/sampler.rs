@@ -1,11 +1,49 @@
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum WrapMode {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
+impl WrapMode {
+    // Maps a texel coordinate outside [0, size) back into the texture
+    pub fn apply(&self, coordinate: i64, size: i64) -> i64 {
+        match self {
+            WrapMode::Repeat => coordinate.rem_euclid(size),
+            WrapMode::MirroredRepeat => {
+                let period = coordinate.rem_euclid(2 * size);
+                if period >= size {2 * size - 1 - period} else {period}
+            },
+            WrapMode::ClampToEdge => coordinate.clamp(0, size - 1),
+        }
+    }
+}
+
+// How a texture is looked up, mirrors the glTF sampler
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub struct Sampler {
-    
+    pub mag_filter: Filter,
+    pub min_filter: Filter,
+    // Filter between mip levels, None when the minification filter does not use mipmaps
+    pub mipmap_filter: Option<Filter>,
+    pub wrap_s: WrapMode,
+    pub wrap_t: WrapMode,
 }
 
 impl Sampler {
     pub fn new() -> Self {
         Self {
-            
+            mag_filter: Filter::Linear,
+            min_filter: Filter::Linear,
+            mipmap_filter: Some(Filter::Linear),
+            wrap_s: WrapMode::Repeat,
+            wrap_t: WrapMode::Repeat,
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,96 @@
+use crate::engine::sampler::*;
+
+use glam::{Vec2};
+
+// Owen-scrambled Sobol points following Burley's "Practical Hash-based Owen Scrambling": every
+// dimension pair is its own shuffled and scrambled 2D Sobol sequence, so any number of
+// dimensions can be drawn while each pair keeps the (0, 2)-sequence stratification
+pub struct SobolSampler {
+    seed: u64,
+    x: u32,
+    y: u32,
+    sample_index: u32,
+    dimension: u32,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed: seed,
+            x: 0,
+            y: 0,
+            sample_index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn next_seed(&mut self) -> u32 {
+        let seed = sample_hash(self.x, self.y, self.dimension, self.seed);
+        self.dimension += 1;
+        seed
+    }
+}
+
+impl SampleGenerator for SobolSampler {
+    fn start_sample(&mut self, x: u32, y: u32, sample_index: u32) {
+        self.x = x;
+        self.y = y;
+        self.sample_index = sample_index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let seed = self.next_seed();
+        let index = nested_uniform_scramble(self.sample_index, seed);
+
+        u32_to_unit_float(nested_uniform_scramble(sobol_dimension_0(index), hash_combine(seed, 0)))
+    }
+
+    fn get_2d(&mut self) -> Vec2 {
+        let seed = self.next_seed();
+        let index = nested_uniform_scramble(self.sample_index, seed);
+
+        Vec2::new(
+            u32_to_unit_float(nested_uniform_scramble(sobol_dimension_0(index), hash_combine(seed, 0))),
+            u32_to_unit_float(nested_uniform_scramble(sobol_dimension_1(index), hash_combine(seed, 1))),
+        )
+    }
+}
+
+// Van der Corput sequence
+fn sobol_dimension_0(index: u32) -> u32 {
+    index.reverse_bits()
+}
+
+fn sobol_dimension_1(mut index: u32) -> u32 {
+    let mut direction: u32 = 1 << 31;
+    let mut result = 0;
+
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= direction;
+        }
+        index >>= 1;
+        direction ^= direction >> 1;
+    }
+
+    result
+}
+
+fn laine_karras_permutation(mut value: u32, seed: u32) -> u32 {
+    value = value.wrapping_add(seed);
+    value ^= value.wrapping_mul(0x6c50b47c);
+    value ^= value.wrapping_mul(0xb82f1e52);
+    value ^= value.wrapping_mul(0xc7afe638);
+    value ^= value.wrapping_mul(0x8d22f6e6);
+    value
+}
+
+fn nested_uniform_scramble(value: u32, seed: u32) -> u32 {
+    laine_karras_permutation(value.reverse_bits(), seed).reverse_bits()
+}
+
+fn hash_combine(seed: u32, value: u32) -> u32 {
+    seed ^ (value.wrapping_add(seed << 6).wrapping_add(seed >> 2))
+        .wrapping_mul(0x9E3779B9)
+}
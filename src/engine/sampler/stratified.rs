@@ -0,0 +1,106 @@
+use crate::engine::math::pcg::*;
+use crate::engine::sampler::*;
+
+use glam::{Vec2};
+
+// Jittered strata, the sample index is mapped to a stratum through a per pixel and dimension
+// permutation so the dimensions stay uncorrelated
+pub struct StratifiedSampler {
+    seed: u64,
+    samples_per_pixel: u32,
+    rng: Pcg32,
+    x: u32,
+    y: u32,
+    sample_index: u32,
+    dimension: u32,
+}
+
+impl StratifiedSampler {
+    pub fn new(seed: u64, samples_per_pixel: u32) -> Self {
+        Self {
+            seed: seed,
+            samples_per_pixel: samples_per_pixel.max(1),
+            rng: Pcg32::new(seed, 0),
+            x: 0,
+            y: 0,
+            sample_index: 0,
+            dimension: 0,
+        }
+    }
+
+    // Stratum of the current sample among `count` strata
+    fn stratum(&mut self, count: u32) -> u32 {
+        let hash = sample_hash(self.x, self.y, self.dimension, self.seed);
+        self.dimension += 1;
+
+        if self.sample_index >= count {
+            return self.rng.next_u32() % count;
+        }
+
+        permute(self.sample_index, count, hash)
+    }
+}
+
+impl SampleGenerator for StratifiedSampler {
+    fn start_sample(&mut self, x: u32, y: u32, sample_index: u32) {
+        self.rng = Pcg32::for_sample(self.seed, x, y, sample_index);
+        self.x = x;
+        self.y = y;
+        self.sample_index = sample_index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let count = self.samples_per_pixel;
+        let stratum = self.stratum(count);
+
+        ((stratum as f32 + self.rng.next_f32()) / count as f32).min(1.0 - f32::EPSILON)
+    }
+
+    fn get_2d(&mut self) -> Vec2 {
+        let resolution = (self.samples_per_pixel as f32).sqrt().ceil() as u32;
+        let stratum = self.stratum(resolution * resolution);
+
+        let x = (stratum % resolution) as f32 + self.rng.next_f32();
+        let y = (stratum / resolution) as f32 + self.rng.next_f32();
+
+        (Vec2::new(x, y) / resolution as f32).min(Vec2::splat(1.0 - f32::EPSILON))
+    }
+}
+
+// Kensler's hashed permutation of [0, length), from "Correlated Multi-Jittered Sampling"
+fn permute(mut index: u32, length: u32, seed: u32) -> u32 {
+    let mut mask = length.wrapping_sub(1);
+    mask |= mask >> 1;
+    mask |= mask >> 2;
+    mask |= mask >> 4;
+    mask |= mask >> 8;
+    mask |= mask >> 16;
+
+    loop {
+        index ^= seed;
+        index = index.wrapping_mul(0xe170893d);
+        index ^= seed >> 16;
+        index ^= (index & mask) >> 4;
+        index ^= seed >> 8;
+        index = index.wrapping_mul(0x0929eb3f);
+        index ^= seed >> 23;
+        index ^= (index & mask) >> 1;
+        index = index.wrapping_mul(1 | seed >> 27);
+        index = index.wrapping_mul(0x6935fa69);
+        index ^= (index & mask) >> 11;
+        index = index.wrapping_mul(0x74dcb303);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0x9e501cc3);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0xc860a3df);
+        index &= mask;
+        index ^= index >> 5;
+
+        if index < length {
+            break;
+        }
+    }
+
+    (index.wrapping_add(seed)) % length
+}
@@ -1,59 +1,159 @@
 use std::vec;
-use glam::{Vec3A, Vec4, Mat4};
-use gltf::Glb;
+use glam::{Vec2, Vec3, Vec3A, Vec4, Mat3A, Mat4, Quat};
+use gltf::texture::{MagFilter, MinFilter, WrappingMode};
 use image::GenericImageView;
 use crate::engine::geometry::bvh::aabb::AABB;
 use crate::engine::material::*;
 use crate::engine::material::diffuse::*;
 use crate::engine::material::diffuse_light::*;
 use crate::engine::material::metal::*;
-use crate::engine::material::normal::*;
 use crate::engine::material::refraction::*;
 use crate::engine::material::pbr::*;
-use crate::engine::material::uv::*;
 use crate::engine::texture::texture2d::*;
 use crate::engine::texture::*;
+use crate::engine::sampler::sampler::*;
 use crate::engine::geometry::bvh::node::*;
 use crate::engine::geometry::bvh::bvh::*;
 use crate::engine::geometry::sphere::*;
 use crate::engine::math::utils::*;
+use crate::engine::accessor::*;
+use crate::engine::animation::*;
+use crate::engine::math::distribution::*;
+use crate::engine::math::ray::*;
 use crate::engine::camera::*;
+use crate::engine::onb::*;
 use std::path::Path;
-use std::ffi::OsStr;
 
 use super::geometry::sphere;
 use super::geometry::traceable::*;
 use super::geometry::triangle::*;
+use super::geometry::instance::*;
 use super::geometry::vertex::Vertex;
+use super::environment::*;
+use super::light::Light;
 use super::light::directional::DirectionalLight;
+use super::light::point::PointLight;
+use super::light::spot::SpotLight;
 use super::profile::*;
 
 use std::io::Cursor;
 use image::io::Reader as ImageReader;
 use image::ColorType;
+use image::ImageFormat;
 
 use data_url::{DataUrl};
 
 use std::sync::{Arc};
+use std::collections::HashMap;
 use std::io;
 use std::fs;
+use std::fmt;
+
+// Geometry of an object that moves with its transform
+#[derive(Clone)]
+enum ObjectPart {
+    // Index into `geometry`
+    Instance(usize, Arc<Instance>),
+    // Indices into `geometry` and `lights` of an emissive triangle, which is kept in world space
+    // so it can be sampled, its object space original and emitted luminance
+    Light(usize, usize, Arc<Triangle>, f32),
+    // Indices into `cameras` and the punctual light lists
+    Camera(usize),
+    DirectionalLight(usize),
+    PointLight(usize),
+    SpotLight(usize),
+}
 
+#[derive(Clone)]
 pub struct Scene {
     pub geometry: Vec<Arc<dyn Traceable>>,
     pub lights: Vec<Arc<dyn Traceable>>,
+    // Emitted power of every entry in `lights`, used as its sampling weight
+    pub light_powers: Vec<f32>,
+    pub light_distribution: Distribution1D,
+    // Index into `lights` by the address of the traceable
+    light_indices: HashMap<usize, usize>,
 
     pub materials: Vec<Arc<dyn Material>>,
+    // Index into `materials` by the address of the material, filled by `build_bvh`
+    material_indices: HashMap<usize, usize>,
+    // Object id of every traceable added through `add_object`, by its address
+    object_ids: HashMap<usize, usize>,
+    // What every object id was loaded into, to move it with `set_object_transform`
+    object_parts: HashMap<usize, Vec<ObjectPart>>,
+    // Object ids of a glTF file are its node indices offset by the nodes of the files loaded before
+    next_object_id: usize,
 
     pub textures: Vec<Arc<Texture>>,
     pub bvh: BVH,
     pub cameras: Vec<Arc<PerspectiveCamera>>,
 
     pub directional_lights: Vec<DirectionalLight>,
+    pub point_lights: Vec<PointLight>,
+    pub spot_lights: Vec<SpotLight>,
+
+    pub environment: Arc<dyn Environment>,
+
+    // Hash of everything the scene was built from, used to validate resumed renders
+    pub fingerprint: u64,
+
+    // Meshes without normals get angle weighted vertex normals instead of flat ones
+    pub smooth_normals: bool,
+
+    // Animations of the loaded glTF, and the time in seconds the scene is posed at
+    pub animation: Option<Animation>,
+    pub time: f32,
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    // File that could not be read
+    Io(String, io::Error),
+    // Not a glTF file, or one that does not follow the specification
+    Parse(String),
+    // Buffer with this index whose data is missing or shorter than it says
+    MissingBuffer(usize),
+    // Primitive of a mesh, by mesh and primitive index, whose vertex data can not be read
+    Accessor(usize, usize, AccessorError),
+    // Image with this index that could not be decoded
+    Image(usize, String),
+    // Animation with this index with a channel that can not be played
+    Animation(usize, String),
+    Unsupported(String),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(path, error) => write!(f, "Scene I/O error: {}: {}", path, error),
+            SceneError::Parse(message) => write!(f, "Invalid glTF: {}", message),
+            SceneError::MissingBuffer(index) => write!(f, "Buffer {} is missing or truncated", index),
+            SceneError::Accessor(mesh, primitive, error) => write!(f, "Primitive {} of mesh {}: {}", primitive, mesh, error),
+            SceneError::Image(index, message) => write!(f, "Image {}: {}", index, message),
+            SceneError::Animation(index, message) => write!(f, "Animation {}: {}", index, message),
+            SceneError::Unsupported(message) => write!(f, "Unsupported glTF feature: {}", message),
+        }
+    }
 }
 
+// Extensions the scene can not be loaded without, if a file requires them
+const SUPPORTED_GLTF_EXTENSIONS: [&str; 1] = ["KHR_lights_punctual"];
+
 struct GLTFContext {
     pub decoded_buffers : Vec<Vec<u8>>,
     pub decoded_images : Vec<Texture>,
+    // By mesh and primitive index, None for primitives that are skipped
+    pub primitives : HashMap<(usize, usize), Option<Arc<GLTFPrimitive>>>,
+    // Object space triangles of the primitives instanced by the nodes
+    pub bvhs : HashMap<(usize, usize), Arc<BVH>>,
+    // By material index, None for the default material
+    pub materials : HashMap<Option<usize>, Arc<dyn Material>>,
+    // Object id of the first node of the file
+    pub first_object_id : usize,
+    // Emitted luminance of the materials averaged over their emissive textures, by material index
+    pub emissions : HashMap<Option<usize>, f32>,
+    // Recoverable issues, what they affect is skipped or left untextured
+    pub warnings : Vec<SceneError>,
 }
 
 impl GLTFContext {
@@ -61,30 +161,413 @@ impl GLTFContext {
         Self{
             decoded_buffers : Vec::new(),
             decoded_images : Vec::new(),
+            primitives : HashMap::new(),
+            bvhs : HashMap::new(),
+            materials : HashMap::new(),
+            first_object_id : 0,
+            emissions : HashMap::new(),
+            warnings : Vec::new(),
         }
     }
 }
 
+// `%XX` escapes of a URI replaced by the bytes they stand for
+fn percent_decode(uri: &str) -> Result<String, SceneError> {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = uri.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or(SceneError::Parse(format!("invalid escape in URI {}", uri)))?;
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(decoded).map_err(|_| SceneError::Parse(format!("URI {} is not UTF-8 once unescaped", uri)))
+}
+
+// Vertex data of a glTF primitive
+struct GLTFPrimitive {
+    pub indices: Vec<u32>,
+    pub positions: Vec<Vec3A>,
+    pub normals: Option<Vec<Vec3A>>,
+    // With the bitangent sign in w
+    pub tangents: Option<Vec<Vec4>>,
+    // By set index
+    pub uvs: Vec<(u32, Vec<Vec2>)>,
+}
+
+impl GLTFPrimitive {
+    // Every index is checked against the vertex count
+    fn read(reader: &AccessorReader, primitive: &gltf::Primitive) -> Result<Self, AccessorError> {
+        let position_accessor = primitive.get(&gltf::Semantic::Positions)
+            .ok_or(AccessorError::MissingAttribute(String::from("POSITION")))?;
+        let positions = reader.read_vec3(&position_accessor)?;
+
+        let vertex_count = positions.len();
+
+        // Every attribute needs one element per vertex
+        let check_count = |accessor: &gltf::Accessor| {
+            if accessor.count() != vertex_count {
+                return Err(AccessorError::Invalid(accessor.index(),
+                    format!("has {} elements for {} vertices", accessor.count(), vertex_count)));
+            }
+            Ok(())
+        };
+
+        let normals = match primitive.get(&gltf::Semantic::Normals) {
+            Some(accessor) => {
+                check_count(&accessor)?;
+                Some(reader.read_vec3(&accessor)?)
+            },
+            None => None,
+        };
+
+        let tangents = match primitive.get(&gltf::Semantic::Tangents) {
+            Some(accessor) => {
+                check_count(&accessor)?;
+                Some(reader.read_vec4(&accessor)?)
+            },
+            None => None,
+        };
+
+        let mut uvs = Vec::new();
+        for (semantic, accessor) in primitive.attributes() {
+            if let gltf::Semantic::TexCoords(set) = semantic {
+                check_count(&accessor)?;
+                uvs.push((set, reader.read_vec2(&accessor)?));
+            }
+        }
+        uvs.sort_by_key(|(set, _)| *set);
+
+        let indices = match primitive.indices() {
+            Some(accessor) => {
+                let indices = reader.read_indices(&accessor)?;
+                if let Some(index) = indices.iter().find(|index| **index as usize >= vertex_count) {
+                    return Err(AccessorError::Invalid(accessor.index(),
+                        format!("index {} is past the {} vertices", index, vertex_count)));
+                }
+                indices
+            },
+            None => (0..vertex_count as u32).collect(),
+        };
+        let indices = Self::triangulate(primitive.mode(), indices);
+
+        Ok(Self {
+            indices: indices,
+            positions: positions,
+            normals: normals,
+            tangents: tangents,
+            uvs: uvs,
+        })
+    }
+
+    // Triangle list of strip and fan primitives, other modes are returned as they are
+    fn triangulate(mode: gltf::mesh::Mode, indices: Vec<u32>) -> Vec<u32> {
+        let count = indices.len();
+        match mode {
+            gltf::mesh::Mode::TriangleStrip => (0..count.saturating_sub(2))
+                .flat_map(|i| if i % 2 == 0 {
+                    [indices[i], indices[i + 1], indices[i + 2]]
+                } else {
+                    [indices[i + 1], indices[i], indices[i + 2]]
+                })
+                .collect(),
+            gltf::mesh::Mode::TriangleFan => (1..count.saturating_sub(1))
+                .flat_map(|i| [indices[i], indices[i + 1], indices[0]])
+                .collect(),
+            _ => indices,
+        }
+    }
+
+    // Unnormalized normal of a triangle, facing the side its vertices wind counterclockwise around
+    fn face_normal(&self, triangle: &[u32]) -> Vec3A {
+        let position = |corner: usize| self.positions[triangle[corner] as usize];
+        (position(1) - position(0)).cross(position(2) - position(0))
+    }
+
+    // Every vertex gets the normals of the triangles around it weighted by their angle at the vertex
+    fn smooth_normals(&self) -> Vec<Vec3A> {
+        let mut normals = vec![Vec3A::ZERO; self.positions.len()];
+
+        for triangle in self.indices.chunks_exact(3) {
+            let normal = self.face_normal(triangle).normalize_or_zero();
+
+            for corner in 0..3 {
+                let position = self.positions[triangle[corner] as usize];
+                let edge1 = (self.positions[triangle[(corner + 1) % 3] as usize] - position).normalize_or_zero();
+                let edge2 = (self.positions[triangle[(corner + 2) % 3] as usize] - position).normalize_or_zero();
+                let angle = edge1.dot(edge2).clamp(-1.0, 1.0).acos();
+
+                normals[triangle[corner] as usize] += normal * angle;
+            }
+        }
+
+        normals.iter().map(|normal| normal.normalize_or_zero()).collect()
+    }
+
+    // Triangles of the primitive placed by `matrix`
+    fn triangles(&self, material: &Arc<dyn Material>, matrix: &Mat4) -> Vec<Triangle> {
+        let normal_matrix = Mat3A::from_mat4(*matrix).inverse().transpose();
+        // Tangents follow the surface, mirroring transforms flip the bitangent
+        let model_matrix = Mat3A::from_mat4(*matrix);
+        let handedness = if model_matrix.determinant() < 0.0 {-1.0} else {1.0};
+
+        self.indices.chunks_exact(3).map(|triangle| {
+            let face_normal = (normal_matrix * self.face_normal(triangle)).normalize_or_zero();
+
+            let vertices = [0, 1, 2].map(|corner| {
+                let index = triangle[corner] as usize;
+
+                let normal = match &self.normals {
+                    Some(normals) => (normal_matrix * normals[index]).normalize_or_zero(),
+                    None => face_normal,
+                };
+                let normal = if normal == Vec3A::ZERO {face_normal} else {normal};
+
+                // Any frame around the normal will do where there is no tangent
+                let mut frame = ONB::build_from_z(normal);
+                if let Some(tangents) = &self.tangents {
+                    let tangent = model_matrix * Vec3A::from(tangents[index].truncate());
+                    let tangent = (tangent - normal * normal.dot(tangent)).normalize_or_zero();
+                    if tangent != Vec3A::ZERO {
+                        let sign = if tangents[index].w < 0.0 {-handedness} else {handedness};
+                        frame.x = tangent;
+                        frame.y = normal.cross(tangent) * sign;
+                    }
+                }
+
+                let uvs = self.uvs.iter().map(|(set, set_uvs)| Vec3A::from((set_uvs[index], *set as f32))).collect();
+
+                Vertex::new(matrix.transform_point3a(self.positions[index]), normal, frame.y, frame.x, uvs)
+            });
+
+            let [vertex1, vertex2, vertex3] = vertices;
+            Triangle::new(material.clone(), vertex1, vertex2, vertex3)
+        }).collect()
+    }
+
+    // Tangents laid out like the TANGENT attribute, generated the way MikkTSpace does: the UV
+    // derivatives of every triangle are projected on the normal plane of its corners and summed
+    // weighted by the corner angles. Vertices without usable UV derivatives get a zero tangent
+    fn generate_tangents(&self, normals: &[Vec3A], uvs: &[Vec2]) -> Vec<Vec4> {
+        let mut tangents = vec![Vec3A::ZERO; self.positions.len()];
+        let mut bitangents = vec![Vec3A::ZERO; self.positions.len()];
+
+        for triangle in self.indices.chunks_exact(3) {
+            for corner in 0..3 {
+                let index = triangle[corner] as usize;
+                let next = triangle[(corner + 1) % 3] as usize;
+                let previous = triangle[(corner + 2) % 3] as usize;
+
+                let edge1 = self.positions[next] - self.positions[index];
+                let edge2 = self.positions[previous] - self.positions[index];
+                // glTF v runs down the image while the normal map green channel points up
+                let delta_uv1 = (uvs[next] - uvs[index]) * Vec2::new(1.0, -1.0);
+                let delta_uv2 = (uvs[previous] - uvs[index]) * Vec2::new(1.0, -1.0);
+
+                let det = delta_uv1.x * delta_uv2.y - delta_uv1.y * delta_uv2.x;
+                if !(det.abs() > Triangle::EPSILON) {
+                    continue;
+                }
+
+                let normal = normals[index];
+                let tangent = (edge1 * delta_uv2.y - edge2 * delta_uv1.y) / det;
+                let bitangent = (edge2 * delta_uv1.x - edge1 * delta_uv2.x) / det;
+                let tangent = (tangent - normal * normal.dot(tangent)).normalize_or_zero();
+                let bitangent = (bitangent - normal * normal.dot(bitangent)).normalize_or_zero();
+                let angle = edge1.normalize_or_zero().dot(edge2.normalize_or_zero()).clamp(-1.0, 1.0).acos();
+
+                tangents[index] += tangent * angle;
+                bitangents[index] += bitangent * angle;
+            }
+        }
+
+        normals.iter().zip(tangents.iter().zip(bitangents.iter())).map(|(normal, (tangent, bitangent))| {
+            let tangent = (*tangent - *normal * normal.dot(*tangent)).normalize_or_zero();
+            let sign = if normal.cross(tangent).dot(*bitangent) < 0.0 {-1.0} else {1.0};
+            Vec4::from((tangent, sign))
+        }).collect()
+    }
+}
+
 impl Scene {
     pub fn new() -> Self {
         Self { 
             bvh: BVH::new(Arc::new(Vec::new())),
             geometry : Vec::new(),
             lights: Vec::new(),
+            light_powers: Vec::new(),
+            light_distribution: Distribution1D::new(Vec::new()),
+            light_indices: HashMap::new(),
             materials : Vec::new(),
+            material_indices: HashMap::new(),
+            object_ids: HashMap::new(),
+            object_parts: HashMap::new(),
+            next_object_id: 0,
             textures : Vec::new(),
             cameras: Vec::new(),
             directional_lights: Vec::new(),
+            point_lights: Vec::new(),
+            spot_lights: Vec::new(),
+            environment: Arc::new(GradientEnvironment{}),
+            fingerprint: 0,
+            smooth_normals: false,
+            animation: None,
+            time: 0.0,
+        }
+    }
+
+    pub fn add_light(&mut self, light: Arc<dyn Traceable>, power: f32) {
+        self.light_indices.insert(Arc::as_ptr(&light) as *const () as usize, self.lights.len());
+        self.lights.push(light);
+        self.light_powers.push(power);
+    }
+
+    pub fn light_weight(&self, index: usize) -> f32 {
+        self.light_distribution.discrete_pdf(index)
+    }
+
+    // Density of sampling `ray.direction` by picking `traceable` from the light list,
+    // zero when it isn't a registered light
+    pub fn light_pdf(&self, traceable: &dyn Traceable, ray: &Ray) -> f32 {
+        let key = traceable as *const dyn Traceable as *const () as usize;
+        match self.light_indices.get(&key) {
+            Some(index) => self.light_weight(*index) * traceable.pdf(ray, 0.001, f32::MAX),
+            None => 0.0,
+        }
+    }
+
+    pub fn add_object(&mut self, traceable: Arc<dyn Traceable>, object_id: usize) {
+        self.object_ids.insert(Arc::as_ptr(&traceable) as *const () as usize, object_id);
+        self.geometry.push(traceable);
+    }
+
+    // Replaces the traceable `add_object` put at `index` of `geometry`
+    fn replace_object(&mut self, index: usize, traceable: Arc<dyn Traceable>) {
+        let old_key = Arc::as_ptr(&self.geometry[index]) as *const () as usize;
+        if let Some(object_id) = self.object_ids.remove(&old_key) {
+            self.object_ids.insert(Arc::as_ptr(&traceable) as *const () as usize, object_id);
+        }
+        self.geometry[index] = traceable;
+    }
+
+    // Places the geometry, cameras and lights loaded for `object_id` with the world `transform`, for
+    // instance for the next frame of an animation. Returns false for unknown or unmovable objects.
+    // The BVH has to be refit or rebuilt before the next render
+    pub fn set_object_transform(&mut self, object_id: usize, transform: &Mat4) -> bool {
+        let mut parts = match self.object_parts.remove(&object_id) {
+            Some(parts) => parts,
+            None => return false,
+        };
+
+        for part in parts.iter_mut() {
+            match part {
+                ObjectPart::Instance(index, instance) => {
+                    *instance = Arc::new(Instance::new(instance.material.clone(), instance.bvh.clone(), *transform));
+                    self.replace_object(*index, instance.clone());
+                },
+                ObjectPart::Light(index, light_index, triangle, emission) => {
+                    let moved = Arc::new(triangle.transformed(transform));
+                    self.light_indices.remove(&(Arc::as_ptr(&self.lights[*light_index]) as *const () as usize));
+                    self.light_indices.insert(Arc::as_ptr(&moved) as *const () as usize, *light_index);
+                    self.lights[*light_index] = moved.clone();
+                    self.light_powers[*light_index] = *emission * moved.area;
+                    self.replace_object(*index, moved);
+                },
+                ObjectPart::Camera(index) => {
+                    self.cameras[*index] = Arc::new(self.cameras[*index].transformed(transform));
+                },
+                ObjectPart::DirectionalLight(index) => {
+                    self.directional_lights[*index].light_vector = Vec3A::from(transform.mul_vec4(Vec4::new(0.0, 0.0, -1.0, 0.0))).normalize();
+                },
+                ObjectPart::PointLight(index) => {
+                    self.point_lights[*index].position = Vec3A::from(transform.mul_vec4(Vec4::new(0.0, 0.0, 0.0, 1.0)));
+                },
+                ObjectPart::SpotLight(index) => {
+                    self.spot_lights[*index].position = Vec3A::from(transform.mul_vec4(Vec4::new(0.0, 0.0, 0.0, 1.0)));
+                    self.spot_lights[*index].light_vector = Vec3A::from(transform.mul_vec4(Vec4::new(0.0, 0.0, -1.0, 0.0))).normalize();
+                },
+            }
         }
+
+        self.object_parts.insert(object_id, parts);
+        true
+    }
+
+    pub fn object_id(&self, traceable: &dyn Traceable) -> Option<usize> {
+        self.object_ids.get(&(traceable as *const dyn Traceable as *const () as usize)).copied()
+    }
+
+    // Primitives in the scene, counting the ones of every instance
+    pub fn primitive_count(&self) -> usize {
+        self.geometry.iter().map(|traceable| traceable.primitive_count()).sum()
+    }
+
+    pub fn material_id(&self, material: &Arc<dyn Material>) -> Option<usize> {
+        self.material_indices.get(&(Arc::as_ptr(material) as *const () as usize)).copied()
+    }
+
+    pub fn add_fingerprint(&mut self, data: &[u8]) {
+        self.fingerprint = hash_bytes(self.fingerprint, data);
+    }
+
+    pub fn punctual_lights(&self) -> impl Iterator<Item = &dyn Light> {
+        self.directional_lights.iter().map(|light| light as &dyn Light)
+            .chain(self.point_lights.iter().map(|light| light as &dyn Light))
+            .chain(self.spot_lights.iter().map(|light| light as &dyn Light))
     }
 
     pub fn build_bvh(&mut self) {
         let bvh_construct_profile = Profile::new("BVH construct", ProfileType::INSTANT);
         self.bvh = BVH::new(Arc::new(self.geometry.clone()));
+        self.light_distribution = Distribution1D::new(self.light_powers.clone());
+        self.material_indices = self.materials.iter().enumerate()
+            .map(|(index, material)| (Arc::as_ptr(material) as *const () as usize, index))
+            .collect();
         drop(bvh_construct_profile);
     }
 
+    // Poses the animated nodes at `time` seconds. The BVH has to be refit or rebuilt before the
+    // next render
+    pub fn set_time(&mut self, time: f32) {
+        let matrices = match &self.animation {
+            Some(animation) => animation.world_matrices(time),
+            None => return,
+        };
+
+        for (node, matrix) in matrices.iter().enumerate() {
+            if let Some(matrix) = matrix {
+                self.set_object_transform(node, matrix);
+            }
+        }
+        self.time = time;
+    }
+
+    // Updates the BVH for objects moved by `set_object_transform`, keeping its tree. Falls back
+    // to a rebuild when objects were added
+    pub fn refit_bvh(&mut self) {
+        if self.bvh.primitives.len() != self.geometry.len() {
+            return self.build_bvh();
+        }
+
+        let bvh_refit_profile = Profile::new("BVH refit", ProfileType::INSTANT);
+        self.bvh.refit(Arc::new(self.geometry.clone()));
+        self.light_distribution = Distribution1D::new(self.light_powers.clone());
+        drop(bvh_refit_profile);
+    }
+
+    // Every primitive using a glTF material shares one instance of it
     fn load_gltf_material(&mut self, context : &mut GLTFContext, material: &gltf::material::Material) -> Arc<dyn Material> {
+        if let Some(loaded) = context.materials.get(&material.index()) {
+            return loaded.clone();
+        }
+
         let mut pbr_material = PBRMaterial::new();
 
         let pbr_metallic_roughness = material.pbr_metallic_roughness();
@@ -93,7 +576,9 @@ impl Scene {
             let base_color_texture = pbr_base_color_texture_option.unwrap();
             let image = &mut context.decoded_images[base_color_texture.texture().source().index()];
             image.set_uv_index(base_color_texture.tex_coord() as usize);
+            image.set_color_space(ColorSpace::SRGB);
             pbr_material.pbr_metallic_roughness.base_color_texture = Arc::new(Texture2D::new(image.clone()));
+            pbr_material.pbr_metallic_roughness.base_color_texture_sampler = Self::load_gltf_sampler(&base_color_texture.texture().sampler());
         }
         let pbr_base_color_factor = pbr_metallic_roughness.base_color_factor();
         pbr_material.pbr_metallic_roughness.base_color_factor = Vec4::from(pbr_base_color_factor);
@@ -103,7 +588,9 @@ impl Scene {
             let metalic_roughness_texture = pbr_metalic_roughness_texture_option.unwrap();
             let image = &mut context.decoded_images[metalic_roughness_texture.texture().source().index()];
             image.set_uv_index(metalic_roughness_texture.tex_coord() as usize);
+            image.set_color_space(ColorSpace::Linear);
             pbr_material.pbr_metallic_roughness.metalic_roughness_texture = Arc::new(Texture2D::new(image.clone()));
+            pbr_material.pbr_metallic_roughness.metalic_roughness_texture_sampler = Self::load_gltf_sampler(&metalic_roughness_texture.texture().sampler());
         }
 
         pbr_material.pbr_metallic_roughness.metalic_factor = pbr_metallic_roughness.metallic_factor();
@@ -114,264 +601,288 @@ impl Scene {
             let normal_texture = normal_texture_option.unwrap();
             let mut image = &mut context.decoded_images[normal_texture.texture().source().index()];
             image.set_uv_index(normal_texture.tex_coord() as usize);
+            image.set_color_space(ColorSpace::Linear);
             pbr_material.pbr_metallic_roughness.normal_texture = Arc::new(Texture2D::new(image.clone()));
+            pbr_material.pbr_metallic_roughness.normal_texture_sampler = Self::load_gltf_sampler(&normal_texture.texture().sampler());
         }
         let occlusion_texture_option = material.occlusion_texture();
         if occlusion_texture_option.is_some() {
             let occlusion_texture = occlusion_texture_option.unwrap();
             let image = &mut context.decoded_images[occlusion_texture.texture().source().index()];
             image.set_uv_index(occlusion_texture.tex_coord() as usize);
+            image.set_color_space(ColorSpace::Linear);
             pbr_material.occlusion_texture = Arc::new(Texture2D::new(image.clone()));
+            pbr_material.occlusion_texture_sampler = Self::load_gltf_sampler(&occlusion_texture.texture().sampler());
         }
         let emissive_texture_option = material.emissive_texture();
         if emissive_texture_option.is_some() {
             let emissive_texture = emissive_texture_option.unwrap();
             let image = &mut context.decoded_images[emissive_texture.texture().source().index()];
             image.set_uv_index(emissive_texture.tex_coord() as usize);
+            image.set_color_space(ColorSpace::SRGB);
             pbr_material.emissive_texture = Arc::new(Texture2D::new(image.clone()));
+            pbr_material.emissive_texture_sampler = Self::load_gltf_sampler(&emissive_texture.texture().sampler());
         }
         pbr_material.emissive_factor = Vec3A::from(material.emissive_factor());
 
-        Arc::new(pbr_material)
+        let pbr_material: Arc<dyn Material> = Arc::new(pbr_material);
+        context.materials.insert(material.index(), pbr_material.clone());
+        self.materials.push(pbr_material.clone());
+        pbr_material
     }
 
-    fn load_gltf_node(&mut self, context : &mut GLTFContext, node: &gltf::Node, matrix: &Mat4) {
-        let node_transform_matrix = node.transform().matrix();
-        let new_matrix = matrix.mul_mat4(&Mat4::from_cols_array_2d(&node_transform_matrix));
+    fn load_gltf_sampler(sampler: &gltf::texture::Sampler) -> Sampler {
+        let mut result = Sampler::new();
 
-        let mesh_option =  node.mesh();
-        if mesh_option.is_some() {
-            let gltf_mesh = mesh_option.unwrap();
-
-            for primitive in gltf_mesh.primitives() {
-                let mut positions: Vec<[Vec3A; 3]> = Vec::new();
-                let mut uvs: Vec<[Vec3A; 3]> = Vec::new();
-                let mut normals: Vec<[Vec3A; 3]> = Vec::new();
-                let mut binormals: Vec<[Vec3A; 3]> = Vec::new();
-                let mut tangents: Vec<[Vec3A; 3]> = Vec::new();
-
-                for attribute in primitive.attributes() {
-                    let sparse_option = attribute.1.sparse();
-                    let buffer_view_option = attribute.1.view();
-                    let raw_type = attribute.1.data_type();
-                    let data_type = attribute.1.dimensions();
-
-                    let primitive_indices_option = primitive.indices();
-                    let buffer_view = buffer_view_option.expect("Error in gltf file, buffer view is empty");
-                    let buffer = &context.decoded_buffers[buffer_view.buffer().index()];
-                    let size = data_type.multiplicity() * raw_type.size();
-                    let mut stride = size; 
-                    if buffer_view.stride().is_some() {
-                        stride = buffer_view.stride().unwrap();
-                    }
+        if let Some(mag_filter) = sampler.mag_filter() {
+            result.mag_filter = match mag_filter {
+                MagFilter::Nearest => Filter::Nearest,
+                MagFilter::Linear => Filter::Linear,
+            };
+        }
 
-                    if sparse_option.is_some() {
-                        let sparse = sparse_option.unwrap();
+        if let Some(min_filter) = sampler.min_filter() {
+            (result.min_filter, result.mipmap_filter) = match min_filter {
+                MinFilter::Nearest => (Filter::Nearest, None),
+                MinFilter::Linear => (Filter::Linear, None),
+                MinFilter::NearestMipmapNearest => (Filter::Nearest, Some(Filter::Nearest)),
+                MinFilter::LinearMipmapNearest => (Filter::Linear, Some(Filter::Nearest)),
+                MinFilter::NearestMipmapLinear => (Filter::Nearest, Some(Filter::Linear)),
+                MinFilter::LinearMipmapLinear => (Filter::Linear, Some(Filter::Linear)),
+            };
+        }
 
-                        let indices_buffer_view = sparse.indices().view();
-                        let indices_buffer = &context.decoded_buffers[indices_buffer_view.buffer().index()];
-                        let indices_size = sparse.indices().index_type().size();
-                        let mut indices_stride = indices_size; 
-                        if indices_buffer_view.stride().is_some() {
-                            indices_stride = indices_buffer_view.stride().unwrap();
-                        }
+        let wrap_mode = |mode: WrappingMode| match mode {
+            WrappingMode::Repeat => WrapMode::Repeat,
+            WrappingMode::MirroredRepeat => WrapMode::MirroredRepeat,
+            WrappingMode::ClampToEdge => WrapMode::ClampToEdge,
+        };
+        result.wrap_s = wrap_mode(sampler.wrap_s());
+        result.wrap_t = wrap_mode(sampler.wrap_t());
 
-                        let mut indices_buffer_pos = 0;
-                        while indices_buffer_pos < indices_buffer_view.length() {
-                            let pos_raw_indices_data_pos = indices_buffer_view.offset() + indices_buffer_pos;
-                            match attribute.0 {
-                                gltf::Semantic::Positions | gltf::Semantic::Normals => {
-                                    let mut decoded_triangle = decode_triangle_vec3_indexed(
-                                        buffer, buffer_view.offset(), stride, raw_type.size(),
-                                        indices_buffer, pos_raw_indices_data_pos, indices_stride, indices_size
-                                    );
-
-                                    decoded_triangle.iter_mut().for_each(|vec| *vec = Vec3A::from(new_matrix.mul_vec4(Vec4::from((*vec, 1.0)))));
-
-                                    match attribute.0 {
-                                        gltf::Semantic::Positions => positions.push(decoded_triangle),
-                                        gltf::Semantic::Normals => normals.push(decoded_triangle),
-                                        _ => println!("Invalid attribute"),
-                                    };
-                                },
-                                gltf::Semantic::TexCoords(set) => {
-                                    let decoded_triangle = decode_triangle_vec2_indexed(
-                                        buffer, buffer_view.offset(), stride, raw_type.size(),
-                                        indices_buffer, pos_raw_indices_data_pos, indices_stride, indices_size
-                                    );
-
-                                    uvs.push([
-                                        Vec3A::from((decoded_triangle[0], set as f32)),
-                                        Vec3A::from((decoded_triangle[1], set as f32)), 
-                                        Vec3A::from((decoded_triangle[2], set as f32)),  
-                                        ]);
-                                },
-                                _ => {
-                                    println!("Unhandled attribute");
-                                    break;
-                                },
-                            };
+        result
+    }
 
-                            indices_buffer_pos += indices_stride * 3;
-                        }
-                    } else {
-                        if primitive_indices_option.is_some() {
-                            let indices = primitive_indices_option.unwrap();
-                            let indices_buffer_view = indices.view().expect("Error in gltf file, indices buffer view is empty, when
-                                indices are not");
-                            let indices_buffer = &context.decoded_buffers[indices_buffer_view.buffer().index()];
-        
-                            let indices_raw_type = indices.data_type();
-                            let indices_data_type = indices.dimensions();
-        
-                            let indices_size = indices_data_type.multiplicity() * indices_raw_type.size();
-                            let mut indices_stride = indices_size; 
-                            if indices_buffer_view.stride().is_some() {
-                                indices_stride = indices_buffer_view.stride().unwrap();
-                            }
-
-                            let mut indices_buffer_pos = 0;
-                            while indices_buffer_pos < indices_buffer_view.length() {
-                                let pos_raw_indices_data_pos = indices_buffer_view.offset() + indices_buffer_pos;
-                                match attribute.0 {
-                                    gltf::Semantic::Positions | gltf::Semantic::Normals => {
-                                        let mut decoded_triangle = decode_triangle_vec3_indexed(
-                                            buffer, buffer_view.offset(), stride, raw_type.size(),
-                                            indices_buffer, pos_raw_indices_data_pos, indices_stride, indices_raw_type.size()
-                                        );
-
-                                        decoded_triangle.iter_mut().for_each(|vec| *vec = Vec3A::from(new_matrix.mul_vec4(Vec4::from((*vec, 1.0)))));
-
-                                        match attribute.0 {
-                                            gltf::Semantic::Positions => positions.push(decoded_triangle),
-                                            gltf::Semantic::Normals => normals.push(decoded_triangle),
-                                            _ => println!("Invalid attribute"),
-                                        };
-                                    },
-                                    gltf::Semantic::TexCoords(set) => {
-                                        let decoded_triangle = decode_triangle_vec2_indexed(
-                                            buffer, buffer_view.offset(), stride, raw_type.size(),
-                                            indices_buffer, pos_raw_indices_data_pos, indices_stride, indices_raw_type.size()
-                                        );
-
-                                        uvs.push([
-                                            Vec3A::from((decoded_triangle[0], set as f32)),
-                                            Vec3A::from((decoded_triangle[1], set as f32)), 
-                                            Vec3A::from((decoded_triangle[2], set as f32)),  
-                                            ]);
-                                    },
-                                    _ => {
-                                        println!("Unhandled attribute");
-                                        break;
-                                    },
-                                };
-
-                                indices_buffer_pos += indices_stride * 3;
-                            }
-                        } else {
-                            let mut buffer_pos = 0;
-                            while buffer_pos < buffer_view.length() {
-                                let pos_raw_data_pos = buffer_view.offset() + buffer_pos;   
-
-                                match attribute.0 {
-                                    gltf::Semantic::Positions | gltf::Semantic::Normals => {
-                                        let mut decoded_triangle =  decode_triangle_vec3(
-                                            buffer, pos_raw_data_pos, stride, raw_type.size()
-                                        );
-
-                                        decoded_triangle.iter_mut().for_each(|vec| *vec = Vec3A::from(new_matrix.mul_vec4(Vec4::from((*vec, 1.0)))));
-
-                                        match attribute.0 {
-                                            gltf::Semantic::Positions => positions.push(decoded_triangle),
-                                            gltf::Semantic::Normals => normals.push(decoded_triangle),
-                                            _ => println!("Invalid attribute"),
-                                        };
-                                    },
-                                    gltf::Semantic::TexCoords(set) => {
-                                        let decoded_triangle = decode_triangle_vec2(
-                                            buffer, pos_raw_data_pos, stride, raw_type.size()
-                                        );
-
-                                        uvs.push([
-                                            Vec3A::from((decoded_triangle[0], set as f32)),
-                                            Vec3A::from((decoded_triangle[1], set as f32)), 
-                                            Vec3A::from((decoded_triangle[2], set as f32)),  
-                                            ]);
-                                    },
-                                    _ => {
-                                        println!("Unhandled attribute");
-                                        break;
-                                    },
-                                }                                     
-
-                                buffer_pos += stride * 3;
-                            }
-                        }                      
-                    }
-                }
+    // Contents of a data URI, or of the file a relative or file URI points to. Files are
+    // fingerprinted, the render depends on them as much as on the glTF itself
+    fn read_gltf_uri(&mut self, directory: &Path, uri: &str) -> Result<Vec<u8>, SceneError> {
+        if uri.starts_with("data:") {
+            let url = DataUrl::process(uri).map_err(|error| SceneError::Parse(format!("invalid data URI, {:?}", error)))?;
+            let (data, _) = url.decode_to_vec().map_err(|error| SceneError::Parse(format!("invalid data URI, {:?}", error)))?;
+            return Ok(data);
+        }
 
-                let material = self.load_gltf_material(context, &primitive.material());
+        let relative = uri.strip_prefix("file://").unwrap_or(uri);
+        if relative.contains("://") {
+            return Err(SceneError::Unsupported(format!("URI {} is not a file", uri)));
+        }
 
-                for (i, normal) in normals.iter_mut().enumerate() {
-                    let triangle_normal = (positions[i][0] - positions[i][1]).cross(positions[i][0] - positions[i][2]).normalize();
+        // Query and fragment are not part of the path
+        let relative = relative.split(|c| c == '?' || c == '#').next().unwrap_or("");
+        let file_path = directory.join(percent_decode(relative)?);
+        let data = fs::read(&file_path).map_err(|error| SceneError::Io(file_path.display().to_string(), error))?;
+        self.add_fingerprint(&data);
+        Ok(data)
+    }
 
-                    let delta_pos1 = positions[i][1] - positions[i][0];
-                    let delta_pos2 = positions[i][2] - positions[i][0];
+    // Vertex data of a triangle primitive with its normals and tangents filled in, None when it
+    // can not be rendered
+    fn read_gltf_primitive(&self, context: &mut GLTFContext, mesh: &gltf::Mesh, primitive: &gltf::Primitive) -> Option<GLTFPrimitive> {
+        match primitive.mode() {
+            gltf::mesh::Mode::Triangles | gltf::mesh::Mode::TriangleStrip | gltf::mesh::Mode::TriangleFan => {},
+            mode => {
+                context.warnings.push(SceneError::Unsupported(format!("primitive {} of mesh {} is made of {:?}, only triangles are rendered",
+                    primitive.index(), mesh.index(), mode)));
+                return None;
+            },
+        }
 
-                    let delta_uv1 = uvs[i][1] - uvs[i][0];
-                    let delta_uv2 = uvs[i][2] - uvs[i][0];
+        let reader = AccessorReader::new(&context.decoded_buffers);
+        let mut vertices = match GLTFPrimitive::read(&reader, primitive) {
+            Ok(vertices) => vertices,
+            Err(error) => {
+                context.warnings.push(SceneError::Accessor(mesh.index(), primitive.index(), error));
+                return None;
+            },
+        };
+
+        // Flat normals when the primitive has none, unless smooth ones are asked for
+        if vertices.normals.is_none() && self.smooth_normals {
+            vertices.normals = Some(vertices.smooth_normals());
+        }
 
-                    let r = 1.0 / (delta_uv1.x * delta_uv2.y - delta_uv1.y * delta_uv2.x);
+        // Tangents of the UV set the normal map is looked up with
+        let tangent_set = primitive.material().normal_texture().map_or(0, |texture| texture.tex_coord());
+        if vertices.tangents.is_none() {
+            if let Some((_, set_uvs)) = vertices.uvs.iter().find(|(set, _)| *set == tangent_set) {
+                let normals = vertices.normals.clone().unwrap_or_else(|| vertices.smooth_normals());
+                vertices.tangents = Some(vertices.generate_tangents(&normals, set_uvs));
+            }
+        }
 
-                    let tangent = (delta_pos1 * delta_uv2.y - delta_pos2 * delta_uv1.y) * r;
-                    let binormal = (delta_pos2 * delta_uv1.x - delta_pos1 * delta_uv2.x) * r; 
+        Some(vertices)
+    }
 
-                    let tangent = [
-                        tangent - triangle_normal.dot(tangent) * triangle_normal, 
-                        tangent - triangle_normal.dot(tangent) * triangle_normal, 
-                        tangent - triangle_normal.dot(tangent) * triangle_normal, 
-                    ];
+    // Node hierarchy and the channels of every animation, when the file has any
+    fn load_gltf_animations(&mut self, context: &mut GLTFContext, gltf: &gltf::Document) {
+        if gltf.animations().len() == 0 {
+            return;
+        }
 
-                    let binormal = [
-                        binormal - triangle_normal.dot(binormal) * triangle_normal - tangent[0].dot(binormal) * tangent[0], 
-                        binormal - triangle_normal.dot(binormal) * triangle_normal - tangent[1].dot(binormal) * tangent[1], 
-                        binormal - triangle_normal.dot(binormal) * triangle_normal - tangent[2].dot(binormal) * tangent[2], 
-                    ];
+        let reader = AccessorReader::new(&context.decoded_buffers);
+        let mut channels = Vec::new();
+
+        for animation in gltf.animations() {
+            for channel in animation.channels() {
+                let target = channel.target();
+                let property = match target.property() {
+                    gltf::animation::Property::Translation => Property::Translation,
+                    gltf::animation::Property::Rotation => Property::Rotation,
+                    gltf::animation::Property::Scale => Property::Scale,
+                    gltf::animation::Property::MorphTargetWeights => {
+                        context.warnings.push(SceneError::Unsupported(format!("morph target weights animated by animation {}", animation.index())));
+                        continue;
+                    },
+                };
+
+                let sampler = channel.sampler();
+                let interpolation = match sampler.interpolation() {
+                    gltf::animation::Interpolation::Step => Interpolation::Step,
+                    gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                    gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+                };
+
+                let times = reader.read_floats(&sampler.input());
+                let values = match property {
+                    Property::Rotation => reader.read_vec4(&sampler.output()),
+                    _ => reader.read_vec3(&sampler.output())
+                        .map(|values| values.into_iter().map(|value| Vec4::from((value, 0.0))).collect()),
+                };
+                let (times, values) = match (times, values) {
+                    (Ok(times), Ok(values)) => (times, values),
+                    (Err(error), _) | (_, Err(error)) => {
+                        context.warnings.push(SceneError::Animation(animation.index(), error.to_string()));
+                        continue;
+                    },
+                };
 
-                    tangents.push(tangent);
-                    binormals.push(binormal);
+                let values_per_key = if interpolation == Interpolation::CubicSpline {3} else {1};
+                if values.len() != times.len() * values_per_key {
+                    context.warnings.push(SceneError::Animation(animation.index(),
+                        format!("{} keys have {} values", times.len(), values.len())));
+                    continue;
                 }
 
-                assert!(positions.len() == 0 || positions.len() == normals.len());
-                assert!(normals.len() == 0||  uvs.len() % normals.len() == 0);
-                let triangles_count = positions.len();
+                channels.push(Channel {
+                    node: context.first_object_id + target.node().index(),
+                    property: property,
+                    interpolation: interpolation,
+                    times: times,
+                    values: values,
+                });
+            }
+        }
 
-                let mut mesh_triangles: Vec<Arc<Box<dyn Traceable>>> = Vec::new();
-                mesh_triangles.reserve(triangles_count);
+        let nodes = gltf.nodes().map(|node| {
+            let (translation, rotation, scale) = node.transform().decomposed();
+            AnimationNode {
+                rest: NodeTransform {
+                    translation: Vec3::from(translation),
+                    rotation: Quat::from_array(rotation),
+                    scale: Vec3::from(scale),
+                },
+                children: node.children().map(|child| context.first_object_id + child.index()).collect(),
+            }
+        });
+
+        // Nodes of files loaded before without animations are not reachable from any root
+        let animation = self.animation.get_or_insert_with(|| Animation {
+            nodes: Vec::new(),
+            roots: Vec::new(),
+            channels: Vec::new(),
+        });
+        animation.nodes.resize_with(context.first_object_id, || AnimationNode {
+            rest: NodeTransform {
+                translation: Vec3::ZERO,
+                rotation: Quat::IDENTITY,
+                scale: Vec3::ONE,
+            },
+            children: Vec::new(),
+        });
+        animation.nodes.extend(nodes);
+        animation.roots.extend(gltf.scenes().flat_map(|scene| scene.nodes().map(|node| context.first_object_id + node.index())));
+        animation.channels.extend(channels);
+    }
 
-                for i in 0..triangles_count {
-                    let uvs_count = uvs.len() / triangles_count;
+    // Light weight per unit area of a material, the emissive factor scaled by the mean of its texture
+    fn gltf_emission(context: &GLTFContext, material: &gltf::Material) -> f32 {
+        let emissive_factor = Vec3A::from(material.emissive_factor());
+        match material.emissive_texture() {
+            Some(emissive_texture) if emissive_factor != Vec3A::ZERO => {
+                let mut image = context.decoded_images[emissive_texture.texture().source().index()].clone();
+                image.set_color_space(ColorSpace::SRGB);
+                luminance(emissive_factor * Vec3A::from(Texture2D::new(image).average().truncate()))
+            },
+            _ => luminance(emissive_factor),
+        }
+    }
 
-                    let mut uvs1 = Vec::new();
-                    let mut uvs2 = Vec::new();
-                    let mut uvs3 = Vec::new();
-                    for j in 0..uvs_count {
-                        uvs1.push(uvs[j * triangles_count + i][0]);
-                        uvs2.push(uvs[j * triangles_count + i][1]);
-                        uvs3.push(uvs[j * triangles_count + i][2]);
-                    }
+    fn load_gltf_node(&mut self, context : &mut GLTFContext, node: &gltf::Node, matrix: &Mat4) {
+        let object_id = context.first_object_id + node.index();
+        let node_transform_matrix = node.transform().matrix();
+        let new_matrix = matrix.mul_mat4(&Mat4::from_cols_array_2d(&node_transform_matrix));
 
-                    let vertex1 = Vertex::new(positions[i][0], normals[i][0], 
-                        binormals[i][0], tangents[i][0], uvs1);
-                    let vertex2 = Vertex::new(positions[i][1], normals[i][1], 
-                        binormals[i][1], tangents[i][1], uvs2);
-                    let vertex3 = Vertex::new(positions[i][2], normals[i][2], 
-                        binormals[i][2], tangents[i][2], uvs3);
+        let mesh_option =  node.mesh();
+        if mesh_option.is_some() {
+            let gltf_mesh = mesh_option.unwrap();
+
+            for primitive in gltf_mesh.primitives() {
+                let key = (gltf_mesh.index(), primitive.index());
+                if !context.primitives.contains_key(&key) {
+                    let vertices = self.read_gltf_primitive(context, &gltf_mesh, &primitive);
+                    context.primitives.insert(key, vertices.map(Arc::new));
+                }
+                let vertices = match &context.primitives[&key] {
+                    Some(vertices) => vertices.clone(),
+                    None => continue,
+                };
 
-                    self.geometry.push(Arc::new(Triangle::new(material.clone(), vertex1, vertex2, vertex3)));
+                let material = self.load_gltf_material(context, &primitive.material());
+                let material_index = primitive.material().index();
+                if !context.emissions.contains_key(&material_index) {
+                    let emission = Self::gltf_emission(context, &primitive.material());
+                    context.emissions.insert(material_index, emission);
+                }
+                let emission = context.emissions[&material_index];
+
+                // Emissive triangles are kept in world space, every one of them is a light
+                if emission > 0.0 {
+                    for triangle in vertices.triangles(&material, &Mat4::IDENTITY) {
+                        let world_triangle = Arc::new(triangle.transformed(&new_matrix));
+                        if world_triangle.area > 0.0 {
+                            self.object_parts.entry(object_id).or_default().push(
+                                ObjectPart::Light(self.geometry.len(), self.lights.len(), Arc::new(triangle), emission));
+                            self.add_light(world_triangle.clone(), emission * world_triangle.area);
+                        }
+
+                        self.add_object(world_triangle, object_id);
+                    }
+                    continue;
                 }
 
-                self.materials.push(material);
+                // Every node using the primitive shares its object space triangles
+                let bvh = context.bvhs.entry(key).or_insert_with(|| {
+                    let triangles: Vec<Arc<dyn Traceable>> = vertices.triangles(&material, &Mat4::IDENTITY).into_iter()
+                        .map(|triangle| Arc::new(triangle) as Arc<dyn Traceable>)
+                        .collect();
+                    Arc::new(BVH::new(Arc::new(triangles)))
+                }).clone();
+
+                if bvh.primitives.len() > 0 {
+                    let instance = Arc::new(Instance::new(material, bvh, new_matrix));
+                    self.object_parts.entry(object_id).or_default().push(ObjectPart::Instance(self.geometry.len(), instance.clone()));
+                    self.add_object(instance, object_id);
+                }
             }
         }
 
@@ -386,6 +897,7 @@ impl Scene {
             match light.kind() {
                 gltf::khr_lights_punctual::Kind::Directional => {
                     let light_vector = Vec3A::from(new_matrix.mul_vec4(Vec4::new(0.0, 0.0, -1.0, 0.0))).normalize();
+                    self.object_parts.entry(object_id).or_default().push(ObjectPart::DirectionalLight(self.directional_lights.len()));
                     self.directional_lights.push(DirectionalLight::new(
                         Vec3A::from(color),
                         intensity / 683.0,
@@ -393,10 +905,28 @@ impl Scene {
                     ));
                 },
                 gltf::khr_lights_punctual::Kind::Spot { inner_cone_angle, outer_cone_angle } => {
-
+                    let position = Vec3A::from(new_matrix.mul_vec4(Vec4::new(0.0, 0.0, 0.0, 1.0)));
+                    let light_vector = Vec3A::from(new_matrix.mul_vec4(Vec4::new(0.0, 0.0, -1.0, 0.0))).normalize();
+                    self.object_parts.entry(object_id).or_default().push(ObjectPart::SpotLight(self.spot_lights.len()));
+                    self.spot_lights.push(SpotLight::new(
+                        Vec3A::from(color),
+                        intensity / 683.0,
+                        position,
+                        light_vector,
+                        light.range(),
+                        inner_cone_angle,
+                        outer_cone_angle
+                    ));
                 },
                 gltf::khr_lights_punctual::Kind::Point => {
-
+                    let position = Vec3A::from(new_matrix.mul_vec4(Vec4::new(0.0, 0.0, 0.0, 1.0)));
+                    self.object_parts.entry(object_id).or_default().push(ObjectPart::PointLight(self.point_lights.len()));
+                    self.point_lights.push(PointLight::new(
+                        Vec3A::from(color),
+                        intensity / 683.0,
+                        position,
+                        light.range()
+                    ));
                 }
             }
         }
@@ -409,6 +939,7 @@ impl Scene {
                     /*self.cameras.push(Arc::new(
                         OrthographicCamera::new()
                     ))*/
+                    context.warnings.push(SceneError::Unsupported(format!("orthographic camera {}", camera.index())));
                 },
                 gltf::camera::Projection::Perspective(perspective) => {
                     let mut aspect_ratio = 1.0;
@@ -425,6 +956,7 @@ impl Scene {
                         name = camera.name().unwrap();
                     }
 
+                    self.object_parts.entry(object_id).or_default().push(ObjectPart::Camera(self.cameras.len()));
                     self.cameras.push(Arc::new(
                         PerspectiveCamera::new(
                             &new_matrix,
@@ -444,43 +976,39 @@ impl Scene {
         }
     }
 
-    pub fn load_gltf(&mut self, path: &str) {
+    // Adds the meshes, lights and cameras of a .gltf or .glb file. Issues that only cost part of the
+    // scene, such as an undecodable image or an unreadable primitive, are returned as warnings
+    pub fn load_gltf(&mut self, path: &str) -> Result<Vec<SceneError>, SceneError> {
         let load_gltf_profile = Profile::new(format!("Load gltf file, {}", path).as_str(), ProfileType::INSTANT);
 
-        let ext = Path::new(path)
-            .extension()
-            .and_then(OsStr::to_str).expect("invalid extension");
+        let data = fs::read(path).map_err(|error| SceneError::Io(String::from(path), error))?;
+        let gltf = gltf::Gltf::from_slice(&data).map_err(|error| SceneError::Parse(error.to_string()))?;
+
+        if let Some(extension) = gltf.extensions_required().find(|extension| !SUPPORTED_GLTF_EXTENSIONS.contains(extension)) {
+            return Err(SceneError::Unsupported(format!("required extension {}", extension)));
+        }
+
+        self.add_fingerprint(&data);
+        self.add_fingerprint(&[self.smooth_normals as u8]);
 
-        let file = fs::File::open(path).expect(format!("Invalid filename: {}", path).as_str());
-        let reader = io::BufReader::new(file);
-        let gltf = gltf::Gltf::from_reader(reader).unwrap();
         let mut context = GLTFContext::new();
+        context.first_object_id = self.next_object_id;
+        self.next_object_id += gltf.nodes().count();
 
-        context.decoded_buffers.resize(gltf.buffers().count(), Vec::new());
-        if (ext == "glb") {
-            let file = fs::File::open(path).expect(format!("Invalid filename: {}", path).as_str());
-            let reader = io::BufReader::new(file);
-            let glb = gltf::Glb::from_reader(reader).unwrap();
-            for buffer in gltf.buffers() {
-                match buffer.source() {
-                    gltf::buffer::Source::Uri(data) => {
-                        
-                    },
-                    gltf::buffer::Source::Bin => {
-                        context.decoded_buffers[buffer.index()] = glb.bin.clone().unwrap().to_vec();
-                    }
-                }
-            }
-        }
+        // Relative URIs are resolved against the directory of the file
+        let directory = Path::new(path).parent().unwrap_or(Path::new(""));
 
+        context.decoded_buffers.resize(gltf.buffers().count(), Vec::new());
         for buffer in gltf.buffers() {
-            match buffer.source() {
-                gltf::buffer::Source::Uri(data) => {
-                    let url = DataUrl::process(data).unwrap();
-                    (context.decoded_buffers[buffer.index()], _) = url.decode_to_vec().unwrap();
-                },
-                gltf::buffer::Source::Bin => {}
+            let data = match buffer.source() {
+                gltf::buffer::Source::Uri(uri) => self.read_gltf_uri(directory, uri)?,
+                gltf::buffer::Source::Bin => gltf.blob.clone().ok_or(SceneError::MissingBuffer(buffer.index()))?,
+            };
+
+            if data.len() < buffer.length() {
+                return Err(SceneError::MissingBuffer(buffer.index()));
             }
+            context.decoded_buffers[buffer.index()] = data;
         }
 
         context.decoded_images.resize(gltf.images().count(), Texture::null());
@@ -489,17 +1017,43 @@ impl Scene {
 
             match image.source() {
                 gltf::image::Source::Uri{ uri, mime_type } => {
-                    let url = DataUrl::process(uri).unwrap();
-                    (image_raw_data, _) = url.decode_to_vec().unwrap();
+                    match self.read_gltf_uri(directory, uri) {
+                        Ok(data) => image_raw_data = data,
+                        Err(error) => {
+                            context.warnings.push(SceneError::Image(image.index(), error.to_string()));
+                            continue;
+                        },
+                    }
                 },
                 gltf::image::Source::View { view, mime_type } => {
                     let buffer = &context.decoded_buffers[view.buffer().index()];
-                    image_raw_data = buffer[view.offset()..view.offset() + view.length()].to_vec();
+                    match buffer.get(view.offset()..view.offset().saturating_add(view.length())) {
+                        Some(data) => image_raw_data = data.to_vec(),
+                        None => {
+                            context.warnings.push(SceneError::Image(image.index(),
+                                format!("buffer view {} is past the end of buffer {}", view.index(), view.buffer().index())));
+                            continue;
+                        },
+                    }
                 },
             }
 
-            let image_reader = ImageReader::new(Cursor::new(image_raw_data));
+            if Texture::is_exr(&image_raw_data) {
+                match Texture::load_exr(&image_raw_data) {
+                    Ok(texture) => context.decoded_images[image.index()] = texture,
+                    Err(error) => context.warnings.push(SceneError::Image(image.index(), error)),
+                }
+                continue;
+            }
+
+            let image_reader = ImageReader::new(Cursor::new(image_raw_data.as_slice()));
             match image_reader.with_guessed_format() {
+                Ok(value) if value.format() == Some(ImageFormat::Hdr) => {
+                    match Texture::load_hdr(&image_raw_data) {
+                        Ok(texture) => context.decoded_images[image.index()] = texture,
+                        Err(error) => context.warnings.push(SceneError::Image(image.index(), error)),
+                    }
+                },
                 Ok(value) => {
                     match value.decode() {
                         Ok(value) => {
@@ -524,12 +1078,12 @@ impl Scene {
                             );
                         },
                         Err(error) => {
-                            println!("Failed to decode image; {}", error.to_string());
+                            context.warnings.push(SceneError::Image(image.index(), error.to_string()));
                         }
                     }
                 },
                 Err(error) => {
-                    println!("Failed to guess format of a texture; {}", error.to_string());
+                    context.warnings.push(SceneError::Image(image.index(), error.to_string()));
                 }
             }
         }
@@ -540,7 +1094,11 @@ impl Scene {
             }
         }
 
+        self.load_gltf_animations(&mut context, &gltf);
+
         drop(load_gltf_profile);
+
+        Ok(context.warnings)
     }
 
     pub fn load_debug(&mut self) {
@@ -548,13 +1106,9 @@ impl Scene {
         let metal_material = Arc::new(
             MetalMaterial{metalness : 0.9}
         );
-        let normal_material = Arc::new(NormalMaterial{diffuse: DiffuseMaterial{}});
         let refraction_material = Arc::new(
             RefractionMaterial{refraction_type: RefractionType::Glass}
         );
-        let uv_material = Arc::new(
-            UVMaterial{diffuse: DiffuseMaterial{}}
-        );
         let diffuse_light_material1 = Arc::new(
             DiffuseLightMaterial{color: Vec3A::new(2.4, 0.1, 0.2)}
         );
@@ -574,25 +1128,22 @@ impl Scene {
         let sphere4 = Arc::new(Sphere::new(diffuse_light_material4.clone(), 0.3, Vec3A::new(8.0, 1.5, -0.5)));
         let sphere5 = Arc::new(Sphere::new(diffuse_light_material4.clone(), 0.3, Vec3A::new(0.0, 0.0, 0.0)));
 
-        //self.lights.push(sphere1.clone());
+        //self.add_light(sphere1.clone(), 1.0);
         //self.geometry.push(sphere1.clone());
         //self.geometry.push(sphere5.clone());
-        //self.lights.push(sphere2.clone());
+        //self.add_light(sphere2.clone(), 1.0);
         //self.geometry.push(sphere2.clone());
-        //self.lights.push(sphere3.clone());
+        //self.add_light(sphere3.clone(), 1.0);
         //self.geometry.push(sphere3.clone());
-        //self.lights.push(sphere4.clone());
+        //self.add_light(sphere4.clone(), 1.0);
         //self.geometry.push(sphere4.clone());
         //self.geometry.push(Arc::new(Sphere{material: diffuse_material.clone(), radius : 100.0, position : Vec3A::new(0.0, -101.0, 1.0)}));
         //self.geometry.push(Arc::new(Sphere{material: metal_material.clone(), radius : 0.5, position : Vec3A::new(1.0, 0.0, 1.2)}));
-        //self.geometry.push(Arc::new(Sphere{material: normal_material.clone(), radius : 0.5, position : Vec3A::new(-1.0, 0.0, 1.2)}));
         //self.geometry.push(Arc::new(Sphere{material: refraction_material.clone(), radius : 0.5, position : Vec3A::new(-1.3, 0.15, 0.5)}));
 
         self.materials.push(diffuse_material.clone());
         self.materials.push(metal_material.clone());
-        self.materials.push(normal_material.clone());
         self.materials.push(refraction_material.clone());
-        self.materials.push(uv_material.clone());
         self.materials.push(diffuse_light_material1.clone());
         self.materials.push(diffuse_light_material2.clone());
         self.materials.push(diffuse_light_material3.clone());
@@ -1,15 +1,124 @@
 pub mod texture2d;
 
-use glam::{Vec2, Vec3A};
+use glam::{Vec2, Vec3A, Vec4};
+
+use crate::engine::tone_mapping::*;
 
 use image::DynamicImage;
+use image::hdr::HdrDecoder;
+
+use std::io::Cursor;
+use std::sync::{Arc, OnceLock};
+
+// Texel values in the precision of the image they come from, normalized when fetched
+enum TexelData {
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+    F32(Vec<f32>),
+}
+
+// Linear values of the 256 sRGB encoded 8 bit values
+fn srgb_table() -> &'static [f32; 256] {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| std::array::from_fn(|value| srgb_eotf(Vec3A::splat(value as f32 / u8::MAX as f32)).x))
+}
+
+// One level of a mip pyramid
+pub struct MipLevel {
+    pub width: u32,
+    pub height: u32,
+    // Values per texel: gray, gray and alpha, RGB or RGBA
+    components: usize,
+    data: Arc<TexelData>,
+    // Color values are sRGB encoded, alpha never is
+    srgb: bool,
+}
+
+impl MipLevel {
+    // RGBA value, integer formats normalized to [0, 1] and decoded to linear. Gray is expanded
+    // and missing alpha is one
+    pub fn texel(&self, x: u32, y: u32) -> Vec4 {
+        let index = (y * self.width + x) as usize * self.components;
+        let alpha = match self.components {
+            2 => 1,
+            4 => 3,
+            _ => usize::MAX,
+        };
+
+        let value = |component: usize| {
+            let srgb = self.srgb && component != alpha;
+            match self.data.as_ref() {
+                TexelData::U8(data) if srgb => srgb_table()[data[index + component] as usize],
+                TexelData::U8(data) => data[index + component] as f32 / u8::MAX as f32,
+                TexelData::U16(data) if srgb => srgb_eotf(Vec3A::splat(data[index + component] as f32 / u16::MAX as f32)).x,
+                TexelData::U16(data) => data[index + component] as f32 / u16::MAX as f32,
+                TexelData::F32(data) => data[index + component],
+            }
+        };
+
+        match self.components {
+            1 => Vec4::new(value(0), value(0), value(0), 1.0),
+            2 => Vec4::new(value(0), value(0), value(0), value(1)),
+            3 => Vec4::new(value(0), value(1), value(2), 1.0),
+            _ => Vec4::new(value(0), value(1), value(2), value(3)),
+        }
+    }
+
+    // Box filtered half resolution level, odd sizes repeat their last row or column. Integer
+    // levels are stored as linear 16 bit values so filtering does not lose precision
+    fn downsample(&self) -> Self {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+
+        let mut values = Vec::with_capacity((width * height) as usize * self.components);
+        for y in 0..height {
+            for x in 0..width {
+                let x0 = (2 * x).min(self.width - 1);
+                let x1 = (2 * x + 1).min(self.width - 1);
+                let y0 = (2 * y).min(self.height - 1);
+                let y1 = (2 * y + 1).min(self.height - 1);
+
+                let texel = (self.texel(x0, y0) + self.texel(x1, y0) + self.texel(x0, y1) + self.texel(x1, y1)) * 0.25;
+                match self.components {
+                    1 => values.push(texel.x),
+                    2 => values.extend([texel.x, texel.w]),
+                    3 => values.extend([texel.x, texel.y, texel.z]),
+                    _ => values.extend(texel.to_array()),
+                }
+            }
+        }
+
+        let data = match self.data.as_ref() {
+            TexelData::F32(_) => TexelData::F32(values),
+            _ => TexelData::U16(values.iter().map(|value| (value.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16).collect()),
+        };
+
+        Self {
+            width: width,
+            height: height,
+            components: self.components,
+            data: Arc::new(data),
+            srgb: false,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ColorSpace {
+    Linear,
+    // 8 and 16 bit color channels are sRGB encoded, alpha and float textures stay linear
+    SRGB,
+}
 
 #[derive(Clone)]
 pub struct Texture {
     dimensions: Vec<u32>,
+    // 4 for float textures
     bytes_per_component: u32,
     components_per_pixel: i32,
-    raw_texture: DynamicImage,
+    color_space: ColorSpace,
+    // Mip pyramid built at load time, the first level is the image as it was loaded
+    levels: Arc<Vec<MipLevel>>,
     uv_index: usize,
 }
 
@@ -19,19 +128,118 @@ impl Texture {
             dimensions: vec![],
             bytes_per_component: 0,
             components_per_pixel: 0,
-            raw_texture: DynamicImage::new_bgr8(0, 0),
+            color_space: ColorSpace::Linear,
+            levels: Arc::new(Vec::new()),
             uv_index: 0,
         }
     }
 
     pub fn new(dimensions: Vec<u32>, bytes_per_component: u32,
         components_per_pixel: i32, raw_texture: DynamicImage) -> Self {
-        Self {
+        let (components, data) = Self::texel_data(raw_texture);
+        Self::from_data(dimensions, bytes_per_component, components_per_pixel, components, data)
+    }
+
+    fn from_data(dimensions: Vec<u32>, bytes_per_component: u32,
+        components_per_pixel: i32, components: usize, data: TexelData) -> Self {
+        let mut texture = Self {
             dimensions: dimensions,
             bytes_per_component: bytes_per_component, 
             components_per_pixel: components_per_pixel,
-            raw_texture: raw_texture,
+            color_space: ColorSpace::Linear,
+            levels: Arc::new(Vec::new()),
             uv_index: 0,
+        };
+        texture.levels = Arc::new(texture.build_mip_levels(components, Arc::new(data)));
+
+        texture
+    }
+
+    // Radiance HDR image, kept as float
+    pub fn load_hdr(data: &[u8]) -> Result<Self, String> {
+        let decoder = HdrDecoder::new(Cursor::new(data)).map_err(|error| error.to_string())?;
+        let metadata = decoder.metadata();
+        let pixels = decoder.read_image_hdr().map_err(|error| error.to_string())?;
+
+        Ok(Self::from_data(vec![metadata.width, metadata.height], 4, 3, 3,
+            TexelData::F32(pixels.iter().flat_map(|pixel| pixel.0).collect())))
+    }
+
+    // First RGB(A) layer of an OpenEXR image, kept as float
+    pub fn load_exr(data: &[u8]) -> Result<Self, String> {
+        use exr::prelude::*;
+
+        let image = read()
+            .no_deep_data()
+            .largest_resolution_level()
+            .rgba_channels(
+                |resolution, _| (resolution.width(), vec![0.0; resolution.width() * resolution.height() * 4]),
+                |(width, values), position, (r, g, b, a): (f32, f32, f32, f32)| {
+                    let index = (position.y() * *width + position.x()) * 4;
+                    values[index..index + 4].copy_from_slice(&[r, g, b, a]);
+                })
+            .first_valid_layer()
+            .all_attributes()
+            .from_buffered(Cursor::new(data))
+            .map_err(|error| error.to_string())?;
+
+        let size = image.layer_data.size;
+        let (_, values) = image.layer_data.channel_data.pixels;
+
+        Ok(Self::from_data(vec![size.width() as u32, size.height() as u32], 4, 4, 4, TexelData::F32(values)))
+    }
+
+    // Full resolution image
+    pub fn base_level(&self) -> &MipLevel {
+        &self.levels[0]
+    }
+
+    // OpenEXR files are not known to the image crate
+    pub fn is_exr(data: &[u8]) -> bool {
+        data.starts_with(&[0x76, 0x2f, 0x31, 0x01])
+    }
+
+    // Raw values of the image and how many there are per texel, formats other than 8 and 16 bit
+    // gray, gray alpha, RGB and RGBA are converted to 8 bit RGBA
+    fn texel_data(image: DynamicImage) -> (usize, TexelData) {
+        match image {
+            DynamicImage::ImageLuma8(buffer) => (1, TexelData::U8(buffer.into_raw())),
+            DynamicImage::ImageLumaA8(buffer) => (2, TexelData::U8(buffer.into_raw())),
+            DynamicImage::ImageRgb8(buffer) => (3, TexelData::U8(buffer.into_raw())),
+            DynamicImage::ImageRgba8(buffer) => (4, TexelData::U8(buffer.into_raw())),
+            DynamicImage::ImageLuma16(buffer) => (1, TexelData::U16(buffer.into_raw())),
+            DynamicImage::ImageLumaA16(buffer) => (2, TexelData::U16(buffer.into_raw())),
+            DynamicImage::ImageRgb16(buffer) => (3, TexelData::U16(buffer.into_raw())),
+            DynamicImage::ImageRgba16(buffer) => (4, TexelData::U16(buffer.into_raw())),
+            _ => (4, TexelData::U8(image.to_rgba8().into_raw())),
+        }
+    }
+
+    // Pyramid over the full resolution `data`, which is shared rather than copied
+    fn build_mip_levels(&self, components: usize, data: Arc<TexelData>) -> Vec<MipLevel> {
+        let float = matches!(data.as_ref(), TexelData::F32(_));
+        let mut levels = vec![MipLevel {
+            width: self.dimensions[0],
+            height: self.dimensions[1],
+            components: components,
+            data: data,
+            srgb: self.color_space == ColorSpace::SRGB && !float,
+        }];
+
+        while levels.last().unwrap().width > 1 || levels.last().unwrap().height > 1 {
+            let level = levels.last().unwrap().downsample();
+            levels.push(level);
+        }
+
+        levels
+    }
+
+    // Mip levels are rebuilt when the texture is used with a different color space
+    pub fn set_color_space(&mut self, color_space: ColorSpace) {
+        if self.color_space != color_space && !self.levels.is_empty() {
+            self.color_space = color_space;
+            let base = &self.levels[0];
+            self.levels = Arc::new(self.build_mip_levels(base.components, base.data.clone()));
         }
     }
 
@@ -49,4 +257,15 @@ impl Texture {
         println!("Invalid uv set");
         return Vec2::ZERO;
     }
-}
\ No newline at end of file
+
+    // Footprint axes of the UV set the texture uses, see `HitResult::uv_footprints`
+    pub fn get_footprint_by_index(&self, uvs: &Vec<Vec3A>, uv_footprints: &Vec<[Vec2; 2]>) -> [Vec2; 2] {
+        for (uv, footprint) in uvs.iter().zip(uv_footprints.iter()) {
+            if uv.z as usize == self.uv_index {
+                return *footprint;
+            }
+        }
+
+        [Vec2::ZERO; 2]
+    }
+}
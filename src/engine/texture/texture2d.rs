@@ -1,7 +1,9 @@
 use crate::engine::sampler::sampler::*;
 use crate::engine::texture::*;
-use glam::{Vec2, Vec3A, Vec4};
-use image::GenericImageView;
+use glam::{DVec4, Vec2, Vec3A, Vec4};
+
+// Most texel lookups spent on one anisotropic sample
+const MAX_ANISOTROPY: f32 = 16.0;
 
 pub struct Texture2D {
     pub texture: Texture
@@ -21,23 +23,109 @@ impl Texture2D {
     }
 
     pub fn valid(&self) -> bool {
-        return self.texture.dimensions.len() == 2
+        return self.texture.dimensions.len() == 2 && !self.texture.levels.is_empty()
+    }
+
+    // Mean of the full resolution texels, one like `sample` when there is no texture
+    pub fn average(&self) -> Vec4 {
+        if !self.valid() {
+            return Vec4::ONE;
+        }
+
+        let level = self.texture.base_level();
+        let mut sum = DVec4::ZERO;
+        for y in 0..level.height {
+            for x in 0..level.width {
+                sum += level.texel(x, y).as_dvec4();
+            }
+        }
+
+        (sum / (level.width as f64 * level.height as f64)).as_vec4()
     }
 
+    // Lookup of the full resolution level with the magnification filter
     pub fn sample(&self, sampler : &Sampler, uv: Vec2) -> Vec4 {
-        let uv = uv.fract();
+        self.sample_footprint(sampler, uv, [Vec2::ZERO; 2])
+    }
 
+    // Lookup filtered over the footprint given by two axes in UV space. Footprints smaller than
+    // a texel are magnified, larger ones pick a mip level from the minor axis and take several
+    // lookups along the major one
+    pub fn sample_footprint(&self, sampler : &Sampler, uv: Vec2, footprint: [Vec2; 2]) -> Vec4 {
         if !self.valid() {
             return Vec4::ONE;
         }
 
-        let x = uv.x * (self.texture.dimensions[0] - 1) as f32;
-        let y = uv.y * (self.texture.dimensions[1] - 1) as f32;
+        let size = Vec2::new(self.texture.dimensions[0] as f32, self.texture.dimensions[1] as f32);
+        let (mut major, mut minor) = (footprint[0], footprint[1]);
+        if (minor * size).length() > (major * size).length() {
+            std::mem::swap(&mut major, &mut minor);
+        }
+
+        let major_length = (major * size).length();
+        let minor_length = (minor * size).length();
+
+        if major_length <= 1.0 {
+            return self.sample_level(sampler.mag_filter, sampler, 0, uv);
+        }
+
+        let mipmap_filter = match sampler.mipmap_filter {
+            Some(mipmap_filter) => mipmap_filter,
+            None => return self.sample_level(sampler.min_filter, sampler, 0, uv),
+        };
+
+        let count = (major_length / minor_length.max(1e-6)).ceil().clamp(1.0, MAX_ANISOTROPY);
+        let max_level = (self.texture.levels.len() - 1) as f32;
+        let lod = (major_length / count).log2().clamp(0.0, max_level);
+
+        let mut color = Vec4::ZERO;
+        for index in 0..count as u32 {
+            let position = uv + major * ((index as f32 + 0.5) / count - 0.5);
+
+            color += match mipmap_filter {
+                Filter::Nearest => self.sample_level(sampler.min_filter, sampler, lod.round() as usize, position),
+                Filter::Linear => {
+                    let level = lod.floor();
+                    let fine = self.sample_level(sampler.min_filter, sampler, level as usize, position);
+                    let coarse = self.sample_level(sampler.min_filter, sampler, (level as usize + 1).min(max_level as usize), position);
+                    fine.lerp(coarse, lod - level)
+                },
+            };
+        }
+
+        color / count
+    }
+
+    fn sample_level(&self, filter: Filter, sampler : &Sampler, level: usize, uv: Vec2) -> Vec4 {
+        let level = &self.texture.levels[level];
+
+        // Texel centers are at half integers
+        let position = uv * Vec2::new(level.width as f32, level.height as f32);
 
-        let color = unsafe {self.texture.raw_texture.unsafe_get_pixel(x as u32, y as u32)};
+        match filter {
+            Filter::Nearest => {
+                self.texel(sampler, level, position.x.floor() as i64, position.y.floor() as i64)
+            },
+            Filter::Linear => {
+                let position = position - Vec2::splat(0.5);
+                let x = position.x.floor();
+                let y = position.y.floor();
+                let fraction_x = position.x - x;
+                let fraction_y = position.y - y;
+                let (x, y) = (x as i64, y as i64);
 
-        let final_color = Vec4::new(color[0] as f32, color[1] as f32, color[2] as f32, color[3] as f32);
+                let top = self.texel(sampler, level, x, y).lerp(self.texel(sampler, level, x + 1, y), fraction_x);
+                let bottom = self.texel(sampler, level, x, y + 1).lerp(self.texel(sampler, level, x + 1, y + 1), fraction_x);
 
-        final_color / (256.0 - 1.0)
+                top.lerp(bottom, fraction_y)
+            },
+        }
     }
-}
\ No newline at end of file
+
+    fn texel(&self, sampler : &Sampler, level: &MipLevel, x: i64, y: i64) -> Vec4 {
+        let x = sampler.wrap_s.apply(x, level.width as i64);
+        let y = sampler.wrap_t.apply(y, level.height as i64);
+
+        level.texel(x as u32, y as u32)
+    }
+}
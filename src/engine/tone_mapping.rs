@@ -0,0 +1,177 @@
+use glam::{Mat3A, Vec3A};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ToneMappingOperator {
+    // Values above 1 are clipped
+    Clamp,
+    Reinhard,
+    // Reinhard mapping `white_point` to 1
+    ExtendedReinhard,
+    // Stephen Hill's fit of the ACES RRT and sRGB ODT
+    ACES,
+    // Polynomial approximation of Troy Sobotka's AgX base look
+    AgX,
+}
+
+impl ToneMappingOperator {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "none" | "clamp" => Some(ToneMappingOperator::Clamp),
+            "reinhard" => Some(ToneMappingOperator::Reinhard),
+            "reinhard_extended" | "extended_reinhard" => Some(ToneMappingOperator::ExtendedReinhard),
+            "aces" => Some(ToneMappingOperator::ACES),
+            "agx" => Some(ToneMappingOperator::AgX),
+            _ => None,
+        }
+    }
+}
+
+// View transform from scene linear radiance to display encoded sRGB in [0, 1], used for LDR outputs
+#[derive(Copy, Clone)]
+pub struct ToneMapping {
+    pub operator: ToneMappingOperator,
+    // Exposure compensation in stops
+    pub exposure: f32,
+    pub white_point: f32,
+    // Adds triangular noise of one quantization step before the 8 bit conversion
+    pub dither: bool,
+}
+
+impl ToneMapping {
+    pub fn new() -> Self {
+        Self {
+            operator: ToneMappingOperator::Clamp,
+            exposure: 0.0,
+            white_point: 4.0,
+            dither: false,
+        }
+    }
+
+    pub fn apply(&self, color: Vec3A) -> Vec3A {
+        let color = color.max(Vec3A::ZERO) * 2.0_f32.powf(self.exposure);
+
+        let mapped = match self.operator {
+            ToneMappingOperator::Clamp => color,
+            ToneMappingOperator::Reinhard => color / (Vec3A::ONE + color),
+            ToneMappingOperator::ExtendedReinhard => {
+                let white_squared = self.white_point * self.white_point;
+                color * (Vec3A::ONE + color / white_squared) / (Vec3A::ONE + color)
+            },
+            ToneMappingOperator::ACES => aces_fitted(color),
+            ToneMappingOperator::AgX => agx(color),
+        };
+
+        srgb_oetf(mapped.clamp(Vec3A::ZERO, Vec3A::ONE))
+    }
+
+    // Converts a [0, 1] display value of pixel (x, y) to 8 bit
+    pub fn quantize(&self, color: Vec3A, x: u32, y: u32) -> [u8; 3] {
+        let mut color = color.clamp(Vec3A::ZERO, Vec3A::ONE) * 255.0;
+
+        if self.dither {
+            color += Vec3A::new(
+                triangular_noise(x, y, 0),
+                triangular_noise(x, y, 1),
+                triangular_noise(x, y, 2),
+            );
+        }
+
+        let color = (color + Vec3A::splat(0.5)).floor().clamp(Vec3A::ZERO, Vec3A::splat(255.0));
+        [color.x as u8, color.y as u8, color.z as u8]
+    }
+}
+
+pub fn srgb_oetf(color: Vec3A) -> Vec3A {
+    let encode = |value: f32| {
+        if value <= 0.0031308 {
+            value * 12.92
+        } else {
+            1.055 * value.powf(1.0 / 2.4) - 0.055
+        }
+    };
+
+    Vec3A::new(encode(color.x), encode(color.y), encode(color.z))
+}
+
+// Inverse of `srgb_oetf`, decodes sRGB values to linear
+pub fn srgb_eotf(color: Vec3A) -> Vec3A {
+    let decode = |value: f32| {
+        if value <= 0.04045 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        }
+    };
+
+    Vec3A::new(decode(color.x), decode(color.y), decode(color.z))
+}
+
+fn aces_fitted(color: Vec3A) -> Vec3A {
+    // sRGB => XYZ => D65_2_D60 => AP1 => RRT_SAT
+    let input = Mat3A::from_cols_array(&[
+        0.59719, 0.35458, 0.04823,
+        0.07600, 0.90834, 0.01566,
+        0.02840, 0.13383, 0.83777,
+    ]).transpose();
+
+    // ODT_SAT => XYZ => D60_2_D65 => sRGB
+    let output = Mat3A::from_cols_array(&[
+        1.60475, -0.53108, -0.07367,
+        -0.10208, 1.10813, -0.00605,
+        -0.00327, -0.07276, 1.07602,
+    ]).transpose();
+
+    let color = input * color;
+    let a = color * (color + Vec3A::splat(0.0245786)) - Vec3A::splat(0.000090537);
+    let b = color * (0.983729 * color + Vec3A::splat(0.4329510)) + Vec3A::splat(0.238081);
+
+    output * (a / b)
+}
+
+fn agx(color: Vec3A) -> Vec3A {
+    const MIN_EV: f32 = -12.47393;
+    const MAX_EV: f32 = 4.026069;
+
+    let inset = Mat3A::from_cols_array(&[
+        0.842479062253094, 0.0423282422610123, 0.0423756549057051,
+        0.0784335999999992, 0.878468636469772, 0.0784336,
+        0.0792237451477643, 0.0791661274605434, 0.879142973793104,
+    ]);
+
+    let outset = Mat3A::from_cols_array(&[
+        1.19687900512017, -0.0528968517574562, -0.0529716355144438,
+        -0.0980208811401368, 1.15190312990417, -0.0980434501171241,
+        -0.0990297440797205, -0.0989611768448433, 1.15107367264116,
+    ]);
+
+    let color = inset * color;
+    let log = Vec3A::new(color.x.log2(), color.y.log2(), color.z.log2()).clamp(
+        Vec3A::splat(MIN_EV), Vec3A::splat(MAX_EV));
+    let x = (log - Vec3A::splat(MIN_EV)) / (MAX_EV - MIN_EV);
+
+    // Sigmoid contrast curve
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    let curve = 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x +
+        0.4298 * x2 + 0.1191 * x - Vec3A::splat(0.00232);
+
+    // The curve output is display encoded, return to linear so the sRGB OETF can be shared
+    let color = (outset * curve).max(Vec3A::ZERO);
+    Vec3A::new(color.x.powf(2.2), color.y.powf(2.2), color.z.powf(2.2))
+}
+
+// Deterministic noise in (-1, 1) with a triangular distribution, hashed from the pixel and channel
+fn triangular_noise(x: u32, y: u32, channel: u32) -> f32 {
+    let uniform = |seed: u32| {
+        let mut hash = seed.wrapping_mul(0x9E3779B9) ^ 0x85EBCA6B;
+        hash ^= hash >> 16;
+        hash = hash.wrapping_mul(0x7FEB352D);
+        hash ^= hash >> 15;
+        hash = hash.wrapping_mul(0x846CA68B);
+        hash ^= hash >> 16;
+        (hash >> 8) as f32 / (1 << 24) as f32
+    };
+
+    let seed = x.wrapping_mul(73856093) ^ y.wrapping_mul(19349663) ^ channel.wrapping_mul(83492791);
+    uniform(seed) - uniform(seed ^ 0x68E31DA4)
+}
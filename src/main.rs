@@ -3,17 +3,66 @@ use pupsy_render::engine::{renderer::Renderer, render_context::RenderContext,
 use std::env;
 use std::process::exit;
 use std::sync::{Arc};
+use std::ops::Range;
+use std::path::Path;
 use pupsy_render::engine::profile::*;
+use pupsy_render::engine::environment::*;
+use pupsy_render::engine::output::*;
+use pupsy_render::engine::aov::*;
+use pupsy_render::engine::tone_mapping::*;
+use pupsy_render::engine::sampler::*;
+use glam::Vec3A;
+
+// Renders every camera of the scene. Outputs, heatmaps and states get the camera index when there
+// are several cameras and the frame number when a frame range is rendered, frames resume from
+// the states that exist
+fn render_cameras(renderer: &Renderer, render_context: &RenderContext, frame: Option<u32>) {
+    let camera_count = render_context.scene.cameras.len();
+    for (index, camera) in render_context.scene.cameras.iter().enumerate() {
+        let path = |path: &str| {
+            let path = if camera_count > 1 {camera_path(path, index)} else {String::from(path)};
+            match frame {
+                Some(frame) => frame_path(path.as_str(), frame),
+                None => path,
+            }
+        };
+
+        let mut context = render_context.clone();
+        context.output = path(render_context.output.as_str());
+        context.heatmap = render_context.heatmap.as_deref().map(path);
+        context.state = render_context.state.as_deref().map(path);
+        context.resume = render_context.resume.as_deref().map(path)
+            .filter(|path| frame.is_none() || Path::new(path).exists());
+
+        if let Err(error) = renderer.render(camera.clone(), Arc::new(context)) {
+            println!("{}", error);
+            exit(-1);
+        }
+    }
+}
 
 fn main() {
     let mut render_context = RenderContext::new();
+    let mut scene = Scene::new();
 
     let total_time = Profile::new(format!("Total Time").as_str(), ProfileType::INSTANT);
 
+    let mut sun_angular_diameter: Option<f32> = None;
+    let mut environment_map: Option<String> = None;
+    let mut environment_color: Option<Vec3A> = None;
+    let mut environment_rotation: f32 = 0.0;
+    let mut environment_intensity: f32 = 1.0;
+    let mut frames: Option<Range<u32>> = None;
+    let mut fps: f32 = 24.0;
+
     let args: Vec<String> = env::args().collect();
+
+    // Read before the loop, meshes are built as soon as `--in` is parsed
+    scene.smooth_normals = args.iter().any(|arg| arg == "--smooth_normals");
+
     for (i, arg) in args.iter().enumerate() {
         if arg == "--debug" {
-            render_context.scene.load_debug();
+            scene.load_debug();
         }
 
         if arg == "--debug_steps" {
@@ -23,7 +72,17 @@ fn main() {
         if arg == "--in" {
             if args.len() > i + 1 {
                 let input_gltf_file = args[i + 1].as_str();
-                render_context.scene.load_gltf(input_gltf_file);
+                match scene.load_gltf(input_gltf_file) {
+                    Ok(warnings) => {
+                        for warning in warnings.iter() {
+                            println!("Warning: {}", warning);
+                        }
+                    },
+                    Err(error) => {
+                        println!("Failed to load {}; {}", input_gltf_file, error);
+                        exit(-1);
+                    },
+                }
             }
             else {
                 println!("Empty input GLTF file");
@@ -42,6 +101,71 @@ fn main() {
             }
         }
 
+        if arg == "--format" {
+            if args.len() > i + 1 {
+                let format = OutputFormat::from_name(args[i + 1].as_str()).expect("Invalid output format");
+                render_context.format = Some(format);
+            }
+            else {
+                println!("Empty output format");
+                exit(-1);
+            }
+        }
+
+        if arg == "--aov" {
+            if args.len() > i + 1 {
+                for name in args[i + 1].split(',') {
+                    if name == "all" {
+                        render_context.aovs.extend(AOV::ALL);
+                    }
+                    else {
+                        render_context.aovs.push(AOV::from_name(name).expect("Invalid AOV name"));
+                    }
+                }
+            }
+            else {
+                println!("Empty AOV list");
+                exit(-1);
+            }
+        }
+
+        if arg == "--tonemap" {
+            if args.len() > i + 1 {
+                let operator = ToneMappingOperator::from_name(args[i + 1].as_str()).expect("Invalid tone mapping operator");
+                render_context.tone_mapping.operator = operator;
+            }
+            else {
+                println!("Empty tone mapping operator");
+                exit(-1);
+            }
+        }
+
+        if arg == "--exposure" {
+            if args.len() > i + 1 {
+                let exposure: f32 = args[i + 1].parse::<f32>().expect("Invalid exposure value");
+                render_context.tone_mapping.exposure = exposure;
+            }
+            else {
+                println!("Empty exposure value");
+                exit(-1);
+            }
+        }
+
+        if arg == "--white_point" {
+            if args.len() > i + 1 {
+                let white_point: f32 = args[i + 1].parse::<f32>().expect("Invalid white point value");
+                render_context.tone_mapping.white_point = white_point;
+            }
+            else {
+                println!("Empty white point value");
+                exit(-1);
+            }
+        }
+
+        if arg == "--dither" {
+            render_context.tone_mapping.dither = true;
+        }
+
         if arg == "--spp" {
             if args.len() > i + 1 {
                 let spp = args[i + 1].parse::<u32>().expect("Invalid spp value");
@@ -53,6 +177,148 @@ fn main() {
             }
         }
 
+        if arg == "--checkpoint_seconds" {
+            if args.len() > i + 1 {
+                let seconds: f32 = args[i + 1].parse::<f32>().expect("Invalid checkpoint interval");
+                render_context.checkpoint_seconds = Some(seconds);
+            }
+            else {
+                println!("Empty checkpoint interval");
+                exit(-1);
+            }
+        }
+
+        if arg == "--checkpoint_passes" {
+            if args.len() > i + 1 {
+                let passes: u32 = args[i + 1].parse::<u32>().expect("Invalid checkpoint pass count");
+                render_context.checkpoint_passes = Some(passes);
+            }
+            else {
+                println!("Empty checkpoint pass count");
+                exit(-1);
+            }
+        }
+
+        if arg == "--state" {
+            if args.len() > i + 1 {
+                render_context.state = Some(args[i + 1].clone());
+            }
+            else {
+                println!("Empty render state file");
+                exit(-1);
+            }
+        }
+
+        if arg == "--resume" {
+            if args.len() > i + 1 {
+                render_context.resume = Some(args[i + 1].clone());
+                if render_context.state.is_none() {
+                    render_context.state = Some(args[i + 1].clone());
+                }
+            }
+            else {
+                println!("Empty render state file");
+                exit(-1);
+            }
+        }
+
+        if arg == "--adaptive" {
+            if args.len() > i + 1 {
+                let threshold: f32 = args[i + 1].parse::<f32>().expect("Invalid adaptive sampling threshold");
+                render_context.adaptive_threshold = Some(threshold);
+            }
+            else {
+                println!("Empty adaptive sampling threshold");
+                exit(-1);
+            }
+        }
+
+        if arg == "--adaptive_min_spp" {
+            if args.len() > i + 1 {
+                let spp = args[i + 1].parse::<u32>().expect("Invalid adaptive sampling minimum spp");
+                render_context.adaptive_min_spp = spp;
+            }
+            else {
+                println!("Empty adaptive sampling minimum spp");
+                exit(-1);
+            }
+        }
+
+        if arg == "--adaptive_max_spp" {
+            if args.len() > i + 1 {
+                let spp = args[i + 1].parse::<u32>().expect("Invalid adaptive sampling maximum spp");
+                render_context.adaptive_max_spp = Some(spp);
+            }
+            else {
+                println!("Empty adaptive sampling maximum spp");
+                exit(-1);
+            }
+        }
+
+        if arg == "--heatmap" {
+            if args.len() > i + 1 {
+                render_context.heatmap = Some(args[i + 1].clone());
+            }
+            else {
+                println!("Empty heat map file");
+                exit(-1);
+            }
+        }
+
+        if arg == "--seed" {
+            if args.len() > i + 1 {
+                let seed = args[i + 1].parse::<u64>().expect("Invalid seed value");
+                render_context.seed = seed;
+            }
+            else {
+                println!("Empty seed value");
+                exit(-1);
+            }
+        }
+
+        if arg == "--frames" {
+            if args.len() > i + 1 {
+                let range = args[i + 1].split_once("..")
+                    .map(|(start, end)| (start.parse::<u32>(), end.parse::<u32>()));
+                match range {
+                    Some((Ok(start), Ok(end))) if start < end => frames = Some(start..end),
+                    _ => {
+                        println!("Invalid frame range, expected start..end with end excluded");
+                        exit(-1);
+                    },
+                }
+            }
+            else {
+                println!("Empty frame range");
+                exit(-1);
+            }
+        }
+
+        if arg == "--fps" {
+            if args.len() > i + 1 {
+                fps = args[i + 1].parse::<f32>().expect("Invalid fps value");
+                if fps.is_nan() || fps <= 0.0 {
+                    println!("Invalid fps value");
+                    exit(-1);
+                }
+            }
+            else {
+                println!("Empty fps value");
+                exit(-1);
+            }
+        }
+
+        if arg == "--sampler" {
+            if args.len() > i + 1 {
+                let sampler = SamplerType::from_name(args[i + 1].as_str()).expect("Invalid sampler");
+                render_context.sampler = sampler;
+            }
+            else {
+                println!("Empty sampler");
+                exit(-1);
+            }
+        }
+
         if arg == "--bounces" {
             if args.len() > i + 1 {
                 let depth: u32 = args[i + 1].parse::<u32>().expect("Invalid depth value");
@@ -64,6 +330,17 @@ fn main() {
             }
         }
 
+        if arg == "--rr_depth" {
+            if args.len() > i + 1 {
+                let depth: u32 = args[i + 1].parse::<u32>().expect("Invalid russian roulette depth value");
+                render_context.russian_roulette_depth = depth;
+            }
+            else {
+                println!("Empty russian roulette depth value");
+                exit(-1);
+            }
+        }
+
         if arg == "--height" {
             if args.len() > i + 1 {
                 let resolution: u32 = args[i + 1].parse::<u32>().expect("Invalid depth value");
@@ -74,17 +351,113 @@ fn main() {
                 exit(-1);
             }
         }
+
+        if arg == "--sun_angle" {
+            if args.len() > i + 1 {
+                let angle: f32 = args[i + 1].parse::<f32>().expect("Invalid sun angle value");
+                sun_angular_diameter = Some(angle.to_radians());
+            }
+            else {
+                println!("Empty sun angle value");
+                exit(-1);
+            }
+        }
+
+        if arg == "--env" {
+            if args.len() > i + 1 {
+                environment_map = Some(args[i + 1].clone());
+            }
+            else {
+                println!("Empty environment map file");
+                exit(-1);
+            }
+        }
+
+        if arg == "--env_color" {
+            if args.len() > i + 1 {
+                let components: Vec<f32> = args[i + 1].split(',')
+                    .map(|component| component.trim().parse::<f32>().expect("Invalid environment color value"))
+                    .collect();
+                if components.len() != 3 {
+                    println!("Environment color must be given as r,g,b");
+                    exit(-1);
+                }
+                environment_color = Some(Vec3A::new(components[0], components[1], components[2]));
+            }
+            else {
+                println!("Empty environment color value");
+                exit(-1);
+            }
+        }
+
+        if arg == "--env_rotation" {
+            if args.len() > i + 1 {
+                let rotation: f32 = args[i + 1].parse::<f32>().expect("Invalid environment rotation value");
+                environment_rotation = rotation.to_radians();
+            }
+            else {
+                println!("Empty environment rotation value");
+                exit(-1);
+            }
+        }
+
+        if arg == "--env_intensity" {
+            if args.len() > i + 1 {
+                environment_intensity = args[i + 1].parse::<f32>().expect("Invalid environment intensity value");
+            }
+            else {
+                println!("Empty environment intensity value");
+                exit(-1);
+            }
+        }
+    }
+
+    let environment_settings = format!("{:?} {:?} {} {} {:?}", environment_map, environment_color,
+        environment_rotation, environment_intensity, sun_angular_diameter);
+    scene.add_fingerprint(environment_settings.as_bytes());
+
+    if environment_map.is_some() {
+        let environment_map = environment_map.unwrap();
+        match EnvironmentMap::load(environment_map.as_str(), environment_rotation, environment_intensity) {
+            Ok(environment) => {
+                scene.environment = Arc::new(environment);
+            },
+            Err(error) => {
+                println!("Failed to load {}; {}", environment_map, error);
+                exit(-1);
+            },
+        }
+    }
+    else if environment_color.is_some() {
+        scene.environment = Arc::new(ConstantEnvironment{
+            color: environment_color.unwrap() * environment_intensity});
+    }
+
+    if sun_angular_diameter.is_some() {
+        for light in scene.directional_lights.iter_mut() {
+            light.angular_diameter = sun_angular_diameter.unwrap();
+        }
     }
 
     // Build bvh
-    render_context.scene.build_bvh();
+    scene.build_bvh();
+
+    render_context.scene = Arc::new(scene);
 
     let renderer = Renderer{};
 
-    let render_context = Arc::new(render_context);
+    match frames {
+        Some(frames) => {
+            for frame in frames {
+                // Only clones the scene if a context of the previous frame still holds it
+                let scene = Arc::make_mut(&mut render_context.scene);
+                scene.set_time(frame as f32 / fps);
+                scene.refit_bvh();
 
-    for (index, camera) in render_context.scene.cameras.iter().enumerate() {
-        renderer.render(camera.clone(),  render_context.clone());
+                render_cameras(&renderer, &render_context, Some(frame));
+            }
+        },
+        None => render_cameras(&renderer, &render_context, None),
     }
 
     drop(total_time);
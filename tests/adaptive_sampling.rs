@@ -0,0 +1,74 @@
+// Adaptive sampling moves the samples converged pixels do not need to the noisy ones.
+
+use std::sync::Arc;
+
+use glam::{Mat4, Vec3, Vec3A};
+use pupsy_render::engine::camera::PerspectiveCamera;
+use pupsy_render::engine::environment::*;
+use pupsy_render::engine::geometry::sphere::Sphere;
+use pupsy_render::engine::material::diffuse::DiffuseMaterial;
+use pupsy_render::engine::render_context::RenderContext;
+use pupsy_render::engine::render_state::*;
+use pupsy_render::engine::renderer::Renderer;
+use pupsy_render::engine::scene::Scene;
+
+const SIZE: u32 = 32;
+const SPP: u32 = 16;
+
+// Diffuse sphere in the middle of a black background, lit by an environment that is only
+// bright above the view, so the sphere is noisy and the background is not
+fn render(name: &str, max_spp: Option<u32>) -> RenderState {
+    let mut scene = Scene::new();
+    scene.geometry.push(Arc::new(Sphere::new(Arc::new(DiffuseMaterial{}), 1.0, Vec3A::ZERO)));
+    scene.environment = Arc::new(EnvironmentMap::new(8, 4,
+        (0..32).map(|i| if i < 8 {Vec3A::splat(4.0)} else {Vec3A::ZERO}).collect(), 0.0, 1.0));
+    scene.build_bvh();
+
+    let state = std::env::temp_dir().join(format!("pupsy_adaptive_{}.state", name)).to_string_lossy().into_owned();
+    let mut render_context = RenderContext::new();
+    render_context.scene = Arc::new(scene);
+    render_context.spp = SPP;
+    render_context.resolution = SIZE;
+    render_context.adaptive_threshold = Some(0.01);
+    render_context.adaptive_min_spp = 4;
+    render_context.adaptive_max_spp = max_spp;
+    render_context.output = std::env::temp_dir().join(format!("pupsy_adaptive_{}.exr", name)).to_string_lossy().into_owned();
+    render_context.state = Some(state.clone());
+
+    let camera = Arc::new(PerspectiveCamera::new(&Mat4::from_translation(Vec3::new(0.0, 0.0, 5.0)),
+        0.8, 1.0, 0.1, 100.0, "camera"));
+    Renderer{}.render(camera, Arc::new(render_context)).unwrap();
+    RenderState::load(state.as_str()).unwrap()
+}
+
+#[test]
+fn noisy_pixels_get_the_samples_flat_ones_leave() {
+    let state = render("budget", None);
+    let samples = &state.frame_buffer.samples;
+
+    // Black background in the corner, lit sphere in the middle
+    let flat = samples[0];
+    let noisy = samples[(SIZE / 2 * SIZE + SIZE / 2) as usize];
+    assert_eq!(flat, 4);
+    assert!(noisy > SPP, "{}", noisy);
+    assert!(noisy <= SPP * 4, "{}", noisy);
+
+    // Everything together stays within the budget of `spp` per pixel
+    let total: u64 = samples.iter().map(|samples| *samples as u64).sum();
+    assert!(total <= (SPP * SIZE * SIZE) as u64, "{}", total);
+}
+
+#[test]
+fn pixels_do_not_go_past_the_maximum() {
+    let state = render("capped", Some(SPP * 2));
+    assert!(state.frame_buffer.samples.iter().all(|samples| *samples <= SPP * 2));
+    assert!(state.frame_buffer.samples.iter().any(|samples| *samples == SPP * 2));
+}
+
+#[test]
+fn high_maximums_spend_the_whole_budget() {
+    let state = render("spent", Some(SPP * 64));
+    let total: u64 = state.frame_buffer.samples.iter().map(|samples| *samples as u64).sum();
+    assert!(total <= (SPP * SIZE * SIZE) as u64, "{}", total);
+    assert!(total > (SPP * SIZE * SIZE) as u64 * 99 / 100, "{}", total);
+}
@@ -0,0 +1,71 @@
+// Samples only depend on the seed, pixel and sample index, not on the thread or order they run in.
+
+use std::sync::Arc;
+use std::thread;
+
+use glam::{Vec3A};
+use pupsy_render::engine::environment::*;
+use pupsy_render::engine::geometry::sphere::Sphere;
+use pupsy_render::engine::material::pbr::PBRMaterial;
+use pupsy_render::engine::math::ray::{Ray, RayCone};
+use pupsy_render::engine::renderer::Renderer;
+use pupsy_render::engine::sampler::*;
+use pupsy_render::engine::scene::Scene;
+
+const PIXELS: u32 = 64;
+const SAMPLES: u32 = 8;
+
+fn scene() -> Scene {
+    let mut material = PBRMaterial::new();
+    material.pbr_metallic_roughness.roughness_factor = 0.4;
+
+    let mut scene = Scene::new();
+    scene.geometry.push(Arc::new(Sphere::new(Arc::new(material), 1.0, Vec3A::ZERO)));
+    scene.environment = Arc::new(EnvironmentMap::new(8, 4,
+        (0..32).map(|i| Vec3A::splat(i as f32 / 8.0)).collect(), 0.0, 1.0));
+    scene.build_bvh();
+    scene
+}
+
+fn render(scene: &Scene, sampler: SamplerType, seed: u64, reverse: bool) -> Vec<Vec3A> {
+    let mut sampler = sampler.create(seed, SAMPLES);
+    let mut pixels = vec![Vec3A::ZERO; PIXELS as usize];
+    let mut order: Vec<u32> = (0..PIXELS).collect();
+    if reverse {
+        order.reverse();
+    }
+
+    for x in order {
+        for sample_index in 0..SAMPLES {
+            let offset = (x as f32 / PIXELS as f32 - 0.5) * 2.4;
+            let ray = Ray{origin: Vec3A::new(offset, 0.3, -5.0), direction: Vec3A::Z, cone: RayCone::ZERO};
+            sampler.start_sample(x, 0, sample_index);
+            pixels[x as usize] += Renderer::sample_scene(&ray, scene, 8, 3, sampler.as_mut());
+        }
+    }
+
+    pixels
+}
+
+#[test]
+fn same_seed_is_bit_identical_across_threads() {
+    let scene = Arc::new(scene());
+
+    for sampler in [SamplerType::Independent, SamplerType::Stratified, SamplerType::Halton, SamplerType::Sobol] {
+        let forward = {
+            let scene = scene.clone();
+            thread::spawn(move || render(&scene, sampler, 7, false))
+        };
+        let reverse = render(&scene, sampler, 7, true);
+
+        assert_eq!(forward.join().unwrap(), reverse, "{}", sampler.name());
+    }
+}
+
+#[test]
+fn different_seeds_differ() {
+    let scene = scene();
+    for sampler in [SamplerType::Independent, SamplerType::Stratified, SamplerType::Halton, SamplerType::Sobol] {
+        assert_ne!(render(&scene, sampler, 1, false), render(&scene, sampler, 2, false), "{}", sampler.name());
+    }
+}
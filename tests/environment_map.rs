@@ -0,0 +1,41 @@
+// Environment maps load through the texture decoders and report bad files as errors.
+
+use std::fs;
+
+use glam::Vec3A;
+use image::Rgb;
+use image::hdr::HdrEncoder;
+use pupsy_render::engine::environment::*;
+
+fn path(name: &str) -> String {
+    std::env::temp_dir().join(name).to_string_lossy().into_owned()
+}
+
+#[test]
+fn hdr_maps_keep_their_pixels() {
+    let mut data = Vec::new();
+    HdrEncoder::new(&mut data).encode(&[Rgb([4.0f32, 0.5, 0.0]), Rgb([0.0, 1.0, 2.0])], 2, 1).unwrap();
+    let file = path("pupsy_environment.hdr");
+    fs::write(file.as_str(), data).unwrap();
+
+    let environment = EnvironmentMap::load(file.as_str(), 0.0, 1.0).unwrap();
+    assert_eq!((environment.width, environment.height), (2, 1));
+    assert_eq!(environment.pixels, vec![Vec3A::new(4.0, 0.5, 0.0), Vec3A::new(0.0, 1.0, 2.0)]);
+}
+
+#[test]
+fn bad_files_are_errors() {
+    assert!(EnvironmentMap::load(path("pupsy_missing_environment.hdr").as_str(), 0.0, 1.0).is_err());
+
+    let file = path("pupsy_environment.png");
+    fs::write(file.as_str(), b"not an environment").unwrap();
+    assert!(EnvironmentMap::load(file.as_str(), 0.0, 1.0).is_err());
+
+    let file = path("pupsy_corrupt_environment.exr");
+    fs::write(file.as_str(), b"not an environment").unwrap();
+    assert!(EnvironmentMap::load(file.as_str(), 0.0, 1.0).is_err());
+
+    let file = path("pupsy_corrupt_environment.hdr");
+    fs::write(file.as_str(), b"not an environment").unwrap();
+    assert!(EnvironmentMap::load(file.as_str(), 0.0, 1.0).is_err());
+}
@@ -0,0 +1,115 @@
+// The accessor reader has to decode every component type, normalization and layout glTF allows.
+
+use glam::{Vec2, Vec3A};
+use pupsy_render::engine::accessor::*;
+
+// glTF document with a single buffer of `length` bytes and the given views and accessors
+fn document(length: usize, views: &str, accessors: &str) -> gltf::Gltf {
+    let json = format!(r#"{{
+        "asset": {{"version": "2.0"}},
+        "buffers": [{{"byteLength": {}}}],
+        "bufferViews": [{}],
+        "accessors": [{}]
+    }}"#, length, views, accessors);
+    gltf::Gltf::from_slice(json.as_bytes()).unwrap()
+}
+
+fn bytes_u16(values: &[u16]) -> Vec<u8> {
+    values.iter().flat_map(|value| value.to_le_bytes()).collect()
+}
+
+fn bytes_f32(values: &[f32]) -> Vec<u8> {
+    values.iter().flat_map(|value| value.to_le_bytes()).collect()
+}
+
+#[test]
+fn interleaved_normalized_uvs_with_offsets() {
+    // Vertices of 8 bytes: a u16 pair used as normalized UVs at offset 4, padding before it
+    let mut buffer = vec![0xAA; 4];
+    for uv in [[0u16, 65535], [32768, 16384]] {
+        buffer.extend_from_slice(&[0xEE; 4]);
+        buffer.extend(bytes_u16(&uv));
+    }
+
+    let gltf = document(buffer.len(),
+        r#"{"buffer": 0, "byteOffset": 4, "byteLength": 16, "byteStride": 8}"#,
+        r#"{"bufferView": 0, "byteOffset": 4, "componentType": 5123, "normalized": true, "count": 2, "type": "VEC2"}"#);
+    let buffers = vec![buffer];
+
+    let uvs = AccessorReader::new(&buffers).read_vec2(&gltf.accessors().next().unwrap()).unwrap();
+    assert_eq!(uvs[0], Vec2::new(0.0, 1.0));
+    assert!((uvs[1] - Vec2::new(0.5, 0.25)).abs().max_element() < 1e-4, "{:?}", uvs[1]);
+}
+
+#[test]
+fn quantized_positions() {
+    // KHR_mesh_quantization: integer positions are used as is unless normalized
+    let buffer: Vec<u8> = [-3i16, 7, 32767, -32768, 0, 1].iter().flat_map(|value| value.to_le_bytes()).collect();
+
+    let gltf = document(buffer.len(),
+        r#"{"buffer": 0, "byteLength": 12}"#,
+        r#"{"bufferView": 0, "componentType": 5122, "count": 1, "type": "VEC3"},
+           {"bufferView": 0, "byteOffset": 6, "componentType": 5122, "normalized": true, "count": 1, "type": "VEC3"}"#);
+    let buffers = vec![buffer];
+    let reader = AccessorReader::new(&buffers);
+    let accessors: Vec<gltf::Accessor> = gltf.accessors().collect();
+
+    assert_eq!(reader.read_vec3(&accessors[0]).unwrap(), vec![Vec3A::new(-3.0, 7.0, 32767.0)]);
+    assert_eq!(reader.read_vec3(&accessors[1]).unwrap(), vec![Vec3A::new(-1.0, 0.0, 1.0 / 32767.0)]);
+}
+
+#[test]
+fn sparse_values_replace_the_base_data() {
+    // Base of three floats, then the sparse indices (u8) and values
+    let mut buffer = bytes_f32(&[1.0, 2.0, 3.0]);
+    buffer.extend_from_slice(&[2, 0, 0, 0]);
+    buffer.extend(bytes_f32(&[9.0]));
+
+    let sparse = r#""sparse": {"count": 1,
+        "indices": {"bufferView": 1, "componentType": 5121},
+        "values": {"bufferView": 2}}"#;
+    let gltf = document(buffer.len(),
+        r#"{"buffer": 0, "byteLength": 12}, {"buffer": 0, "byteOffset": 12, "byteLength": 1}, {"buffer": 0, "byteOffset": 16, "byteLength": 4}"#,
+        format!(r#"{{"bufferView": 0, "componentType": 5126, "count": 3, "type": "SCALAR", {}}},
+            {{"componentType": 5126, "count": 3, "type": "SCALAR", {}}}"#, sparse, sparse).as_str());
+    let buffers = vec![buffer];
+    let reader = AccessorReader::new(&buffers);
+    let accessors: Vec<gltf::Accessor> = gltf.accessors().collect();
+
+    assert_eq!(reader.read_floats(&accessors[0]).unwrap(), vec![1.0, 2.0, 9.0]);
+    // Without a buffer view the base is zeros
+    assert_eq!(reader.read_floats(&accessors[1]).unwrap(), vec![0.0, 0.0, 9.0]);
+}
+
+#[test]
+fn indices_of_every_width() {
+    let mut buffer = vec![1u8, 2, 250, 0];
+    buffer.extend(bytes_u16(&[3, 60000]));
+    buffer.extend(70000u32.to_le_bytes());
+
+    let gltf = document(buffer.len(),
+        r#"{"buffer": 0, "byteLength": 12}"#,
+        r#"{"bufferView": 0, "componentType": 5121, "count": 3, "type": "SCALAR"},
+           {"bufferView": 0, "byteOffset": 4, "componentType": 5123, "count": 2, "type": "SCALAR"},
+           {"bufferView": 0, "byteOffset": 8, "componentType": 5125, "count": 1, "type": "SCALAR"},
+           {"bufferView": 0, "componentType": 5126, "count": 1, "type": "SCALAR"}"#);
+    let buffers = vec![buffer];
+    let reader = AccessorReader::new(&buffers);
+    let accessors: Vec<gltf::Accessor> = gltf.accessors().collect();
+
+    assert_eq!(reader.read_indices(&accessors[0]).unwrap(), vec![1, 2, 250]);
+    assert_eq!(reader.read_indices(&accessors[1]).unwrap(), vec![3, 60000]);
+    assert_eq!(reader.read_indices(&accessors[2]).unwrap(), vec![70000]);
+    assert!(matches!(reader.read_indices(&accessors[3]), Err(AccessorError::Invalid(3, _))));
+}
+
+#[test]
+fn reads_past_the_view_are_errors() {
+    let gltf = document(16,
+        r#"{"buffer": 0, "byteLength": 8}"#,
+        r#"{"bufferView": 0, "componentType": 5126, "count": 3, "type": "SCALAR"}"#);
+    let accessor = gltf.accessors().next().unwrap();
+
+    assert!(matches!(AccessorReader::new(&[vec![0; 16]]).read_floats(&accessor), Err(AccessorError::OutOfBounds(0))));
+    assert!(matches!(AccessorReader::new(&[]).read_floats(&accessor), Err(AccessorError::MissingBuffer(0))));
+}
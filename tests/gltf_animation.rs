@@ -0,0 +1,237 @@
+// Node animations evaluated at a time, and the scene posed with them for every frame.
+
+use std::fs;
+use std::sync::Arc;
+
+use glam::{Mat4, Quat, Vec3, Vec3A, Vec4};
+use pupsy_render::engine::animation::{Channel, Interpolation, Property};
+use pupsy_render::engine::math::ray::{Ray, RayCone};
+use pupsy_render::engine::output::{camera_path, frame_path};
+use pupsy_render::engine::render_context::RenderContext;
+use pupsy_render::engine::scene::Scene;
+
+fn channel(property: Property, interpolation: Interpolation, times: &[f32], values: &[Vec4]) -> Channel {
+    Channel {
+        node: 0,
+        property: property,
+        interpolation: interpolation,
+        times: times.to_vec(),
+        values: values.to_vec(),
+    }
+}
+
+#[test]
+fn step_and_linear_channels_hold_outside_their_keys() {
+    let values = [Vec4::new(0.0, 0.0, 0.0, 0.0), Vec4::new(10.0, 0.0, 0.0, 0.0), Vec4::new(10.0, 20.0, 0.0, 0.0)];
+
+    let step = channel(Property::Translation, Interpolation::Step, &[1.0, 2.0, 4.0], &values);
+    assert_eq!(step.sample(0.0), values[0]);
+    assert_eq!(step.sample(1.9), values[0]);
+    assert_eq!(step.sample(2.0), values[1]);
+    assert_eq!(step.sample(9.0), values[2]);
+
+    let linear = channel(Property::Translation, Interpolation::Linear, &[1.0, 2.0, 4.0], &values);
+    assert_eq!(linear.sample(0.0), values[0]);
+    assert!((linear.sample(1.5) - Vec4::new(5.0, 0.0, 0.0, 0.0)).length() < 1e-5);
+    assert!((linear.sample(3.0) - Vec4::new(10.0, 10.0, 0.0, 0.0)).length() < 1e-5);
+    assert_eq!(linear.sample(9.0), values[2]);
+}
+
+#[test]
+fn linear_rotations_are_slerped() {
+    let start = Quat::IDENTITY;
+    let end = Quat::from_rotation_y(std::f32::consts::FRAC_PI_2);
+    let rotation = channel(Property::Rotation, Interpolation::Linear, &[0.0, 1.0], &[Vec4::from(start), Vec4::from(end)]);
+
+    let halfway = Quat::from_vec4(rotation.sample(0.5));
+    assert!(halfway.angle_between(Quat::from_rotation_y(std::f32::consts::FRAC_PI_4)) < 1e-4);
+    assert!((Vec4::from(halfway).length() - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn cubic_splines_follow_their_tangents() {
+    // In tangent, value and out tangent per key
+    let flat = [Vec4::ZERO, Vec4::ZERO, Vec4::ZERO, Vec4::ZERO, Vec4::X, Vec4::ZERO];
+    let spline = channel(Property::Translation, Interpolation::CubicSpline, &[0.0, 2.0], &flat);
+    assert_eq!(spline.sample(0.0), Vec4::ZERO);
+    assert!((spline.sample(1.0) - Vec4::X * 0.5).length() < 1e-5);
+    assert_eq!(spline.sample(2.0), Vec4::X);
+    // Flat tangents ease in, so a quarter of the way is less than a quarter of the value
+    assert!(spline.sample(0.5).x < 0.25);
+
+    // With tangents of the straight line between the keys it is that line, tangents are per second
+    let straight = [Vec4::ZERO, Vec4::ZERO, Vec4::X * 0.5, Vec4::X * 0.5, Vec4::X, Vec4::ZERO];
+    let spline = channel(Property::Translation, Interpolation::CubicSpline, &[0.0, 2.0], &straight);
+    assert!((spline.sample(0.5) - Vec4::X * 0.25).length() < 1e-5);
+}
+
+// .glb container of `json` and its binary chunk
+fn glb(json: &str, bin: &[u8]) -> Vec<u8> {
+    let mut json = json.as_bytes().to_vec();
+    while json.len() % 4 != 0 {
+        json.push(b' ');
+    }
+
+    let mut chunks = Vec::new();
+    chunks.extend((json.len() as u32).to_le_bytes());
+    chunks.extend(b"JSON");
+    chunks.extend(json);
+    chunks.extend((bin.len() as u32).to_le_bytes());
+    chunks.extend(b"BIN\0");
+    chunks.extend(bin);
+
+    let mut data = Vec::new();
+    data.extend(b"glTF");
+    data.extend(2u32.to_le_bytes());
+    data.extend((12 + chunks.len() as u32).to_le_bytes());
+    data.extend(chunks);
+    data
+}
+
+// Upward facing triangle on node 0, moved linearly from x = 0 to x = 10 over the first second,
+// carrying a point light on node 1 and a camera on node 2 one unit above it. Node 3 holds a
+// triangle at z = 5 that is not animated
+fn load(name: &str) -> Scene {
+    let mut scene = Scene::new();
+    load_into(&mut scene, name);
+    scene.build_bvh();
+    scene
+}
+
+fn load_into(scene: &mut Scene, name: &str) {
+    let floats: [f32; 17] = [
+        0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0,
+        0.0, 1.0,
+        0.0, 0.0, 0.0, 10.0, 0.0, 0.0,
+    ];
+    let bin: Vec<u8> = floats.iter().flat_map(|value| value.to_le_bytes()).collect();
+
+    let json = format!(r#"{{
+        "asset": {{"version": "2.0"}},
+        "extensionsUsed": ["KHR_lights_punctual"],
+        "extensions": {{"KHR_lights_punctual": {{"lights": [{{"type": "point", "intensity": 683}}]}}}},
+        "buffers": [{{"byteLength": {length}}}],
+        "bufferViews": [
+            {{"buffer": 0, "byteLength": 36}},
+            {{"buffer": 0, "byteOffset": 36, "byteLength": 8}},
+            {{"buffer": 0, "byteOffset": 44, "byteLength": 24}}
+        ],
+        "accessors": [
+            {{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 0, 1]}},
+            {{"bufferView": 1, "componentType": 5126, "count": 2, "type": "SCALAR", "min": [0], "max": [1]}},
+            {{"bufferView": 2, "componentType": 5126, "count": 2, "type": "VEC3"}}
+        ],
+        "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0}}}}]}}],
+        "cameras": [{{"type": "perspective", "perspective": {{"yfov": 1.0, "znear": 0.1}}}}],
+        "nodes": [
+            {{"mesh": 0, "children": [1, 2]}},
+            {{"translation": [0, 1, 0], "extensions": {{"KHR_lights_punctual": {{"light": 0}}}}}},
+            {{"translation": [0, 1, 0], "camera": 0}},
+            {{"mesh": 0, "translation": [0, 0, 5]}}
+        ],
+        "animations": [{{
+            "samplers": [{{"input": 1, "output": 2, "interpolation": "LINEAR"}}],
+            "channels": [{{"sampler": 0, "target": {{"node": 0, "path": "translation"}}}}]
+        }}],
+        "scenes": [{{"nodes": [0, 3]}}]
+    }}"#, length = bin.len());
+
+    let path = std::env::temp_dir().join(format!("pupsy_animation_{}.glb", name));
+    fs::write(&path, glb(&json, &bin)).unwrap();
+
+    let warnings = scene.load_gltf(path.to_str().unwrap()).unwrap();
+    assert!(warnings.is_empty(), "{:?}", warnings);
+}
+
+// Object id of what a ray straight down at `x`, `z` hits
+fn object_at(scene: &Scene, x: f32, z: f32) -> Option<usize> {
+    let ray = Ray{origin: Vec3A::new(x, 10.0, z), direction: -Vec3A::Y, cone: RayCone::ZERO};
+    match scene.bvh.hit(&ray, 0.001, f32::MAX) {
+        (Some(_), traceable) => scene.object_id(traceable),
+        (None, _) => None,
+    }
+}
+
+#[test]
+fn nodes_and_their_children_follow_the_animation() {
+    let mut scene = load("posed");
+    assert!((scene.animation.as_ref().unwrap().duration() - 1.0).abs() < 1e-6);
+    assert_eq!(object_at(&scene, 0.2, 0.2), Some(0));
+
+    scene.set_time(0.5);
+    scene.refit_bvh();
+    assert_eq!(object_at(&scene, 0.2, 0.2), None);
+    assert_eq!(object_at(&scene, 5.2, 0.2), Some(0));
+    assert!((scene.point_lights[0].position - Vec3A::new(5.0, 1.0, 0.0)).length() < 1e-5);
+    assert!((scene.cameras[0].camera.transform.translation - Vec3A::new(5.0, 1.0, 0.0)).length() < 1e-5);
+
+    // Held at the last key
+    scene.set_time(3.0);
+    scene.refit_bvh();
+    assert_eq!(object_at(&scene, 10.2, 0.2), Some(0));
+
+    // The node without animation stays where it was loaded
+    assert_eq!(object_at(&scene, 0.2, 5.2), Some(3));
+}
+
+#[test]
+fn every_file_gets_its_own_object_ids() {
+    let mut scene = Scene::new();
+    load_into(&mut scene, "first");
+    load_into(&mut scene, "second");
+    scene.build_bvh();
+
+    // The second file takes the ids after the four nodes of the first, so its still triangle moves alone
+    assert!(scene.set_object_transform(7, &Mat4::from_translation(Vec3::new(0.0, 0.0, 20.0))));
+    assert!(!scene.set_object_transform(8, &Mat4::IDENTITY));
+    scene.refit_bvh();
+    assert_eq!(object_at(&scene, 0.2, 5.2), Some(3));
+    assert_eq!(object_at(&scene, 0.2, 20.2), Some(7));
+
+    // The animations of both files move their own nodes
+    scene.set_time(0.5);
+    scene.refit_bvh();
+    assert_eq!(object_at(&scene, 0.2, 0.2), None);
+    let ray = Ray{origin: Vec3A::new(5.2, 10.0, 0.2), direction: -Vec3A::Y, cone: RayCone::ZERO};
+    let (_, traceable) = scene.bvh.hit(&ray, 0.001, f32::MAX);
+    assert!(matches!(scene.object_id(traceable), Some(0) | Some(4)));
+    assert!(scene.point_lights.iter().all(|light| (light.position - Vec3A::new(5.0, 1.0, 0.0)).length() < 1e-5));
+    assert_eq!(scene.point_lights.len(), 2);
+}
+
+#[test]
+fn posing_a_shared_scene_leaves_the_other_frames_alone() {
+    let mut render_context = RenderContext::new();
+    render_context.scene = Arc::new(load("shared"));
+    let previous_frame = render_context.clone();
+
+    let scene = Arc::make_mut(&mut render_context.scene);
+    scene.set_time(0.5);
+    scene.refit_bvh();
+
+    assert_eq!(object_at(&render_context.scene, 5.2, 0.2), Some(0));
+    assert_eq!(object_at(&previous_frame.scene, 0.2, 0.2), Some(0));
+    assert_eq!(previous_frame.scene.time, 0.0);
+}
+
+#[test]
+fn scenes_without_animations_ignore_the_time() {
+    let mut scene = Scene::new();
+    scene.set_time(1.0);
+    assert!(scene.animation.is_none());
+    assert_eq!(scene.time, 0.0);
+}
+
+#[test]
+fn frames_are_numbered_before_the_extension() {
+    assert_eq!(frame_path("render.png", 7), "render.0007.png");
+    assert_eq!(frame_path("out/render.exr", 120), "out/render.0120.exr");
+    assert_eq!(frame_path("render", 3), "render.0003");
+}
+
+#[test]
+fn cameras_are_numbered_before_the_frame() {
+    assert_eq!(camera_path("render.png", 1), "render.camera1.png");
+    assert_eq!(frame_path(camera_path("out/render.exr", 0).as_str(), 12), "out/render.camera0.0012.exr");
+    assert_eq!(camera_path("render", 2), "render.camera2");
+}
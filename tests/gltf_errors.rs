@@ -0,0 +1,132 @@
+// Files that can not be loaded, or only partly, are reported instead of aborting the process.
+
+use std::fs;
+
+use pupsy_render::engine::accessor::AccessorError;
+use pupsy_render::engine::scene::{Scene, SceneError};
+
+// Loads `contents` from a file named `name`
+fn load(name: &str, contents: &[u8]) -> (Scene, Result<Vec<SceneError>, SceneError>) {
+    let path = std::env::temp_dir().join(format!("pupsy_errors_{}", name));
+    fs::write(&path, contents).unwrap();
+
+    let mut scene = Scene::new();
+    let result = scene.load_gltf(path.to_str().unwrap());
+    (scene, result)
+}
+
+// Document with one triangle reading its three positions from a buffer of `length` bytes in a
+// .glb binary chunk, plus the `extra` top level properties
+fn triangle_json(length: usize, accessor_count: usize, extra: &str) -> String {
+    format!(r#"{{
+        "asset": {{"version": "2.0"}},
+        {extra}
+        "buffers": [{{"byteLength": {length}}}],
+        "bufferViews": [{{"buffer": 0, "byteLength": {length}}}],
+        "accessors": [{{"bufferView": 0, "componentType": 5126, "count": {count}, "type": "VEC3",
+            "min": [0, 0, 0], "max": [1, 1, 0]}}],
+        "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0}}}}]}}],
+        "nodes": [{{"mesh": 0}}],
+        "scenes": [{{"nodes": [0]}}]
+    }}"#, extra = extra, length = length, count = accessor_count)
+}
+
+// .glb container of `json` and, if given, a binary chunk
+fn glb(json: &str, bin: Option<&[u8]>) -> Vec<u8> {
+    let mut json = json.as_bytes().to_vec();
+    while json.len() % 4 != 0 {
+        json.push(b' ');
+    }
+
+    let mut chunks = Vec::new();
+    chunks.extend((json.len() as u32).to_le_bytes());
+    chunks.extend(b"JSON");
+    chunks.extend(json);
+    if let Some(bin) = bin {
+        chunks.extend((bin.len() as u32).to_le_bytes());
+        chunks.extend(b"BIN\0");
+        chunks.extend(bin);
+    }
+
+    let mut data = Vec::new();
+    data.extend(b"glTF");
+    data.extend(2u32.to_le_bytes());
+    data.extend((12 + chunks.len() as u32).to_le_bytes());
+    data.extend(chunks);
+    data
+}
+
+fn triangle_bin() -> Vec<u8> {
+    [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].iter().flat_map(|value| value.to_le_bytes()).collect()
+}
+
+#[test]
+fn valid_glb_loads_without_warnings() {
+    let bin = triangle_bin();
+    let (scene, result) = load("valid.glb", &glb(&triangle_json(bin.len(), 3, ""), Some(&bin)));
+    assert!(result.unwrap().is_empty());
+    assert_eq!(scene.primitive_count(), 1);
+}
+
+#[test]
+fn missing_files_are_io_errors() {
+    let mut scene = Scene::new();
+    let result = scene.load_gltf("/nonexistent/pupsy.gltf");
+    assert!(matches!(result, Err(SceneError::Io(_, _))), "{:?}", result);
+}
+
+#[test]
+fn invalid_documents_are_parse_errors() {
+    let (_, result) = load("garbage.gltf", b"{ not json");
+    assert!(matches!(result, Err(SceneError::Parse(_))), "{:?}", result);
+}
+
+#[test]
+fn missing_binary_chunks_are_missing_buffers() {
+    let (_, result) = load("no_bin.glb", &glb(&triangle_json(36, 3, ""), None));
+    assert!(matches!(result, Err(SceneError::MissingBuffer(0))), "{:?}", result);
+
+    // Shorter than the buffer says
+    let (_, result) = load("short_bin.glb", &glb(&triangle_json(36, 3, ""), Some(&triangle_bin()[..24])));
+    assert!(matches!(result, Err(SceneError::MissingBuffer(0))), "{:?}", result);
+}
+
+#[test]
+fn unknown_required_extensions_are_unsupported() {
+    let bin = triangle_bin();
+    let json = triangle_json(bin.len(), 3, r#""extensionsUsed": ["KHR_draco_mesh_compression"], "extensionsRequired": ["KHR_draco_mesh_compression"],"#);
+    let (scene, result) = load("draco.glb", &glb(&json, Some(&bin)));
+    assert!(matches!(&result, Err(SceneError::Unsupported(message)) if message.contains("KHR_draco_mesh_compression")), "{:?}", result);
+    assert_eq!(scene.primitive_count(), 0);
+}
+
+#[test]
+fn unreadable_primitives_are_warnings() {
+    // The accessor reads four positions from a buffer holding three
+    let bin = triangle_bin();
+    let (scene, result) = load("bad_accessor.glb", &glb(&triangle_json(bin.len(), 4, ""), Some(&bin)));
+    let warnings = result.unwrap();
+    assert!(matches!(warnings.as_slice(), [SceneError::Accessor(0, 0, AccessorError::OutOfBounds(0))]), "{:?}", warnings);
+    assert_eq!(scene.primitive_count(), 0);
+}
+
+#[test]
+fn image_views_past_their_buffer_are_warnings() {
+    let bin = triangle_bin();
+    let json = format!(r#"{{
+        "asset": {{"version": "2.0"}},
+        "buffers": [{{"byteLength": {length}}}],
+        "bufferViews": [{{"buffer": 0, "byteLength": {length}}}, {{"buffer": 0, "byteOffset": 24, "byteLength": 64}}],
+        "accessors": [{{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+            "min": [0, 0, 0], "max": [1, 1, 0]}}],
+        "images": [{{"bufferView": 1, "mimeType": "image/png"}}],
+        "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0}}}}]}}],
+        "nodes": [{{"mesh": 0}}],
+        "scenes": [{{"nodes": [0]}}]
+    }}"#, length = bin.len());
+
+    let (scene, result) = load("image_view.glb", &glb(&json, Some(&bin)));
+    let warnings = result.unwrap();
+    assert!(matches!(warnings.as_slice(), [SceneError::Image(0, _)]), "{:?}", warnings);
+    assert_eq!(scene.primitive_count(), 1);
+}
@@ -0,0 +1,96 @@
+// .gltf files referencing buffers and images next to them, as exporters write them by default.
+
+use std::fs;
+use std::path::PathBuf;
+
+use pupsy_render::engine::scene::{Scene, SceneError};
+
+// Directory holding `name`.gltf, whose one triangle is read from `data/my mesh.bin` and whose
+// material uses `textures/albedo #1.png` filled with `color`
+fn write_scene(name: &str, color: [u8; 4]) -> PathBuf {
+    write_scene_with_material(name, color, r#"{"pbrMetallicRoughness": {"baseColorTexture": {"index": 0}}}"#)
+}
+
+fn write_scene_with_material(name: &str, color: [u8; 4], material: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("pupsy_external_{}", name));
+    fs::create_dir_all(directory.join("data")).unwrap();
+    fs::create_dir_all(directory.join("textures")).unwrap();
+
+    let positions: [f32; 9] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+    let data: Vec<u8> = positions.iter().flat_map(|value| value.to_le_bytes()).collect();
+    fs::write(directory.join("data").join("my mesh.bin"), &data).unwrap();
+
+    image::RgbaImage::from_pixel(2, 2, image::Rgba(color))
+        .save(directory.join("textures").join("albedo #1.png")).unwrap();
+
+    let json = format!(r#"{{
+        "asset": {{"version": "2.0"}},
+        "buffers": [{{"byteLength": {length}, "uri": "data/my%20mesh.bin"}}],
+        "bufferViews": [{{"buffer": 0, "byteLength": {length}}}],
+        "accessors": [{{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+            "min": [0, 0, 0], "max": [1, 1, 0]}}],
+        "images": [{{"uri": "textures/albedo%20%231.png"}}],
+        "textures": [{{"source": 0}}],
+        "materials": [{material}],
+        "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0}}, "material": 0}}]}}],
+        "nodes": [{{"mesh": 0}}],
+        "scenes": [{{"nodes": [0]}}]
+    }}"#, length = data.len(), material = material);
+
+    let path = directory.join(format!("{}.gltf", name));
+    fs::write(&path, json).unwrap();
+    path
+}
+
+fn load(path: &PathBuf) -> Scene {
+    let mut scene = Scene::new();
+    let warnings = scene.load_gltf(path.to_str().unwrap()).unwrap();
+    assert!(warnings.is_empty(), "{:?}", warnings);
+    scene
+}
+
+#[test]
+fn relative_buffers_are_read_from_the_gltf_directory() {
+    let path = write_scene("buffer", [255, 0, 0, 255]);
+    let scene = load(&path);
+    assert_eq!(scene.primitive_count(), 1);
+}
+
+#[test]
+fn relative_images_are_part_of_the_fingerprint() {
+    let red = load(&write_scene("red", [255, 0, 0, 255]));
+    let red_again = load(&write_scene("red_again", [255, 0, 0, 255]));
+    let green = load(&write_scene("green", [0, 255, 0, 255]));
+
+    // The glTF files are identical, only the image next to them differs
+    assert_eq!(red.fingerprint, red_again.fingerprint);
+    assert_ne!(red.fingerprint, green.fingerprint);
+}
+
+#[test]
+fn missing_images_are_warnings_and_missing_buffers_errors() {
+    let path = write_scene("missing", [255, 0, 0, 255]);
+    fs::remove_file(path.parent().unwrap().join("textures").join("albedo #1.png")).unwrap();
+    let mut scene = Scene::new();
+    let warnings = scene.load_gltf(path.to_str().unwrap()).unwrap();
+    assert!(matches!(warnings.as_slice(), [SceneError::Image(0, _)]), "{:?}", warnings);
+    assert_eq!(scene.primitive_count(), 1);
+
+    fs::remove_file(path.parent().unwrap().join("data").join("my mesh.bin")).unwrap();
+    let mut scene = Scene::new();
+    let result = scene.load_gltf(path.to_str().unwrap());
+    assert!(matches!(result, Err(SceneError::Io(_, _))), "{:?}", result);
+    assert_eq!(scene.primitive_count(), 0);
+}
+
+#[test]
+fn emissive_textures_scale_the_light_power() {
+    let material = r#"{"emissiveFactor": [2, 2, 2], "emissiveTexture": {"index": 0}}"#;
+    let white = load(&write_scene_with_material("emissive_white", [255, 255, 255, 255], material));
+    let gray = load(&write_scene_with_material("emissive_gray", [188, 188, 188, 255], material));
+
+    // Half a unit of area emitting 2, and the sRGB gray is half as bright
+    assert_eq!(white.light_powers.len(), 1);
+    assert!((white.light_powers[0] - 1.0).abs() < 1e-4, "{}", white.light_powers[0]);
+    assert!((gray.light_powers[0] - 0.5).abs() < 0.005, "{}", gray.light_powers[0]);
+}
@@ -0,0 +1,183 @@
+// Nodes sharing a mesh are instances of one copy of its triangles, placed by their transforms.
+
+use std::fs;
+
+use glam::{Mat4, Quat, Vec2, Vec3, Vec3A};
+use pupsy_render::engine::math::ray::{Ray, RayCone};
+use pupsy_render::engine::geometry::traceable::HitResult;
+use pupsy_render::engine::scene::Scene;
+
+// .glb container of `json` and its binary chunk
+fn glb(json: &str, bin: &[u8]) -> Vec<u8> {
+    let mut json = json.as_bytes().to_vec();
+    while json.len() % 4 != 0 {
+        json.push(b' ');
+    }
+
+    let mut chunks = Vec::new();
+    chunks.extend((json.len() as u32).to_le_bytes());
+    chunks.extend(b"JSON");
+    chunks.extend(json);
+    chunks.extend((bin.len() as u32).to_le_bytes());
+    chunks.extend(b"BIN\0");
+    chunks.extend(bin);
+
+    let mut data = Vec::new();
+    data.extend(b"glTF");
+    data.extend(2u32.to_le_bytes());
+    data.extend((12 + chunks.len() as u32).to_le_bytes());
+    data.extend(chunks);
+    data
+}
+
+// One upward facing triangle with UVs equal to its x and z, used by a plain node, one moved to
+// x = 5 and doubled in size, and one moved to x = -5 and mirrored along x
+fn load(name: &str, emissive: bool) -> Scene {
+    let positions: [f32; 9] = [0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0];
+    let uvs: [f32; 6] = [0.0, 0.0, 0.0, 1.0, 1.0, 0.0];
+    let bin: Vec<u8> = positions.iter().chain(uvs.iter()).flat_map(|value| value.to_le_bytes()).collect();
+
+    let json = format!(r#"{{
+        "asset": {{"version": "2.0"}},
+        "buffers": [{{"byteLength": {length}}}],
+        "bufferViews": [{{"buffer": 0, "byteLength": 36}}, {{"buffer": 0, "byteOffset": 36, "byteLength": 24}}],
+        "accessors": [
+            {{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 0, 1]}},
+            {{"bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC2"}}
+        ],
+        "materials": [{{"emissiveFactor": [{emission}, {emission}, {emission}]}}],
+        "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0, "TEXCOORD_0": 1}}, "material": 0}}]}}],
+        "nodes": [
+            {{"mesh": 0}},
+            {{"mesh": 0, "translation": [5, 0, 0], "scale": [2, 2, 2]}},
+            {{"mesh": 0, "translation": [-5, 0, 0], "scale": [-1, 1, 1]}}
+        ],
+        "scenes": [{{"nodes": [0, 1, 2]}}]
+    }}"#, length = bin.len(), emission = if emissive {1} else {0});
+
+    let path = std::env::temp_dir().join(format!("pupsy_instancing_{}.glb", name));
+    fs::write(&path, glb(&json, &bin)).unwrap();
+
+    let mut scene = Scene::new();
+    let warnings = scene.load_gltf(path.to_str().unwrap()).unwrap();
+    assert!(warnings.is_empty(), "{:?}", warnings);
+    scene.build_bvh();
+    scene
+}
+
+// Hit of a ray straight down at `x`, `z` with a cone `width` wide, and the object id of the hit
+fn hit_at(scene: &Scene, x: f32, z: f32, width: f32) -> Option<(HitResult, Option<usize>)> {
+    let ray = Ray{origin: Vec3A::new(x, 10.0, z), direction: -Vec3A::Y, cone: RayCone{width: width, spread: 0.0}};
+    match scene.bvh.hit(&ray, 0.001, f32::MAX) {
+        (Some(mut hit_result), traceable) => {
+            traceable.finalize(&ray, &mut hit_result);
+            Some((hit_result, scene.object_id(traceable)))
+        },
+        (None, _) => None,
+    }
+}
+
+#[test]
+fn nodes_share_the_mesh_triangles() {
+    let scene = load("shared", false);
+    assert_eq!(scene.geometry.len(), 3);
+    assert_eq!(scene.primitive_count(), 3);
+    assert_eq!(scene.materials.len(), 1);
+    assert!(scene.lights.is_empty());
+}
+
+#[test]
+fn instances_are_hit_where_their_nodes_place_them() {
+    let scene = load("placed", false);
+
+    for (x, z, node, uv) in [
+        (0.2, 0.3, 0, Vec2::new(0.2, 0.3)),
+        (5.4, 0.6, 1, Vec2::new(0.2, 0.3)),
+        (-5.2, 0.3, 2, Vec2::new(0.2, 0.3)),
+    ] {
+        let (hit_result, object_id) = hit_at(&scene, x, z, 0.0).expect("ray missed the instance");
+        assert_eq!(object_id, Some(node));
+        assert!((hit_result.t - 10.0).abs() < 1e-4, "{}", hit_result.t);
+        assert!((hit_result.position - Vec3A::new(x, 0.0, z)).length() < 1e-4);
+        assert!((hit_result.normal - Vec3A::Y).length() < 1e-5, "{:?}", hit_result.normal);
+        assert!((Vec2::new(hit_result.uvs[0].x, hit_result.uvs[0].y) - uv).length() < 1e-4);
+    }
+
+    // Outside of the triangles, the doubled one reaches past x = 6 and the mirrored one is at x < -5
+    assert!(hit_at(&scene, 0.8, 0.8, 0.0).is_none());
+    assert!(hit_at(&scene, 6.4, 0.2, 0.0).is_some());
+    assert!(hit_at(&scene, -4.8, 0.2, 0.0).is_none());
+}
+
+#[test]
+fn footprints_are_measured_in_object_space() {
+    let scene = load("footprints", false);
+
+    // The same cone covers half as much of the texture of the instance twice as large
+    let (plain, _) = hit_at(&scene, 0.2, 0.3, 0.1).unwrap();
+    let (doubled, _) = hit_at(&scene, 5.4, 0.6, 0.1).unwrap();
+    let plain_width = plain.uv_footprints[0][0].length().max(plain.uv_footprints[0][1].length());
+    let doubled_width = doubled.uv_footprints[0][0].length().max(doubled.uv_footprints[0][1].length());
+    assert!((plain_width - 0.1).abs() < 1e-4, "{}", plain_width);
+    assert!((doubled_width - 0.05).abs() < 1e-4, "{}", doubled_width);
+}
+
+#[test]
+fn emissive_meshes_stay_lights_in_world_space() {
+    let scene = load("emissive", true);
+    assert_eq!(scene.geometry.len(), 3);
+    assert_eq!(scene.lights.len(), 3);
+
+    let (hit_result, object_id) = hit_at(&scene, 5.4, 0.6, 0.0).unwrap();
+    assert_eq!(object_id, Some(1));
+    assert!((hit_result.t - 10.0).abs() < 1e-4);
+}
+
+#[test]
+fn moved_instances_are_found_after_a_refit_or_rebuild() {
+    for rebuild in [false, true] {
+        let mut scene = load(if rebuild {"moved_rebuilt"} else {"moved_refit"}, false);
+
+        // Node 1 goes from x = 5 to z = 20, at its original size
+        assert!(scene.set_object_transform(1, &Mat4::from_translation(Vec3::new(0.0, 0.0, 20.0))));
+        if rebuild {
+            scene.build_bvh();
+        } else {
+            scene.refit_bvh();
+        }
+
+        assert!(hit_at(&scene, 5.4, 0.6, 0.0).is_none());
+        let (hit_result, object_id) = hit_at(&scene, 0.2, 20.3, 0.0).expect("ray missed the moved instance");
+        assert_eq!(object_id, Some(1));
+        assert!((Vec2::new(hit_result.uvs[0].x, hit_result.uvs[0].y) - Vec2::new(0.2, 0.3)).length() < 1e-4);
+
+        // The others did not move
+        assert_eq!(hit_at(&scene, 0.2, 0.3, 0.0).unwrap().1, Some(0));
+        assert_eq!(hit_at(&scene, -5.2, 0.3, 0.0).unwrap().1, Some(2));
+    }
+}
+
+#[test]
+fn moved_lights_keep_their_power_in_step() {
+    let mut scene = load("moved_light", true);
+    let power = scene.light_powers[0];
+
+    assert!(scene.set_object_transform(0, &Mat4::from_scale_rotation_translation(Vec3::splat(3.0), Quat::IDENTITY, Vec3::new(0.0, 0.0, 20.0))));
+    scene.refit_bvh();
+
+    assert!((scene.light_powers[0] - power * 9.0).abs() < 1e-4);
+    let (hit_result, object_id) = hit_at(&scene, 0.6, 20.9, 0.0).expect("ray missed the moved light");
+    assert_eq!(object_id, Some(0));
+    assert!((hit_result.t - 10.0).abs() < 1e-4);
+
+    // Sampling the moved light finds it where it now is
+    let ray = Ray{origin: Vec3A::new(0.6, 10.0, 20.9), direction: -Vec3A::Y, cone: RayCone::ZERO};
+    let (_, traceable) = scene.bvh.hit(&ray, 0.001, f32::MAX);
+    assert!(scene.light_pdf(traceable, &ray) > 0.0);
+}
+
+#[test]
+fn unknown_objects_are_not_moved() {
+    let mut scene = load("unknown", false);
+    assert!(!scene.set_object_transform(7, &Mat4::IDENTITY));
+}
@@ -0,0 +1,175 @@
+// Primitives missing optional attributes or using other topologies than triangle lists still have
+// to load into triangles.
+
+use std::fs;
+
+use glam::{Vec3A};
+use pupsy_render::engine::math::ray::{Ray, RayCone};
+use pupsy_render::engine::scene::{Scene, SceneError};
+use pupsy_render::engine::geometry::traceable::HitResult;
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let value = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(value >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+// Loads a mesh of one primitive with the given positions and mode, from a file named `name`
+fn load(name: &str, positions: &[[f32; 3]], mode: u32, smooth_normals: bool) -> Scene {
+    load_attributes(name, positions, &[], mode, smooth_normals)
+}
+
+// Same with more float attributes, given by semantic, accessor type and values
+fn load_attributes(name: &str, positions: &[[f32; 3]], attributes: &[(&str, &str, Vec<f32>)], mode: u32, smooth_normals: bool) -> Scene {
+    let mut data: Vec<u8> = positions.iter().flatten().flat_map(|value| value.to_le_bytes()).collect();
+    let mut views = vec![format!(r#"{{"buffer": 0, "byteLength": {}}}"#, data.len())];
+    let mut accessors = vec![format!(r#"{{"bufferView": 0, "componentType": 5126, "count": {}, "type": "VEC3",
+        "min": [-10, -10, -10], "max": [10, 10, 10]}}"#, positions.len())];
+    let mut semantics = vec![String::from(r#""POSITION": 0"#)];
+
+    for (semantic, accessor_type, values) in attributes {
+        let bytes: Vec<u8> = values.iter().flat_map(|value| value.to_le_bytes()).collect();
+        views.push(format!(r#"{{"buffer": 0, "byteOffset": {}, "byteLength": {}}}"#, data.len(), bytes.len()));
+        accessors.push(format!(r#"{{"bufferView": {}, "componentType": 5126, "count": {}, "type": "{}"}}"#,
+            views.len() - 1, positions.len(), accessor_type));
+        semantics.push(format!(r#""{}": {}"#, semantic, accessors.len() - 1));
+        data.extend(bytes);
+    }
+
+    let json = format!(r#"{{
+        "asset": {{"version": "2.0"}},
+        "buffers": [{{"byteLength": {length}, "uri": "data:application/octet-stream;base64,{data}"}}],
+        "bufferViews": [{views}],
+        "accessors": [{accessors}],
+        "meshes": [{{"primitives": [{{"attributes": {{{semantics}}}, "mode": {mode}}}]}}],
+        "nodes": [{{"mesh": 0}}],
+        "scenes": [{{"nodes": [0]}}]
+    }}"#, length = data.len(), data = base64(&data), views = views.join(", "), accessors = accessors.join(", "),
+        semantics = semantics.join(", "), mode = mode);
+
+    let path = std::env::temp_dir().join(format!("pupsy_primitives_{}.gltf", name));
+    fs::write(&path, json).unwrap();
+
+    let mut scene = Scene::new();
+    scene.smooth_normals = smooth_normals;
+    let warnings = scene.load_gltf(path.to_str().unwrap()).unwrap();
+    // Only the skipped primitive modes are expected to be warned about
+    assert!(warnings.iter().all(|warning| matches!(warning, SceneError::Unsupported(_))), "{:?}", warnings);
+    scene.build_bvh();
+    scene
+}
+
+// Where a ray straight down hits the scene
+fn hit_at(scene: &Scene, x: f32, z: f32) -> HitResult {
+    let ray = Ray{origin: Vec3A::new(x, 10.0, z), direction: -Vec3A::Y, cone: RayCone::ZERO};
+    let (hit_result, _) = scene.bvh.hit(&ray, 0.001, f32::MAX);
+    hit_result.expect("ray missed the mesh")
+}
+
+// Shading normal where a ray straight down hits the scene
+fn normal_at(scene: &Scene, x: f32, z: f32) -> Vec3A {
+    hit_at(scene, x, z).normal
+}
+
+const TRIANGLES: u32 = 4;
+const TRIANGLE_STRIP: u32 = 5;
+const TRIANGLE_FAN: u32 = 6;
+const LINES: u32 = 1;
+
+#[test]
+fn positions_only_get_flat_normals() {
+    let scene = load("flat", &[[0.0, 0.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]], TRIANGLES, false);
+    assert_eq!(scene.primitive_count(), 1);
+    assert!((normal_at(&scene, 0.2, 0.2) - Vec3A::Y).length() < 1e-5);
+}
+
+#[test]
+fn strips_and_fans_are_triangulated() {
+    let quad = [[0.0, 0.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [1.0, 0.0, 1.0]];
+    let strip = load("strip", &quad, TRIANGLE_STRIP, false);
+    assert_eq!(strip.primitive_count(), 2);
+    // Every other strip triangle is flipped back to the same winding
+    assert!((normal_at(&strip, 0.2, 0.2) - Vec3A::Y).length() < 1e-5);
+    assert!((normal_at(&strip, 0.8, 0.8) - Vec3A::Y).length() < 1e-5);
+
+    let fan = load("fan", &[[0.0, 0.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 1.0], [1.0, 0.0, 0.0]], TRIANGLE_FAN, false);
+    assert_eq!(fan.primitive_count(), 2);
+    assert!((normal_at(&fan, 0.8, 0.2) - Vec3A::Y).length() < 1e-5);
+}
+
+#[test]
+fn lines_are_skipped() {
+    let scene = load("lines", &[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0]], LINES, false);
+    assert_eq!(scene.primitive_count(), 0);
+}
+
+#[test]
+fn smooth_normals_are_shared_between_triangles() {
+    // Strip of two slopes of a roof meeting at the ridge x = 0, the ridge vertices are shared
+    let roof = [[-1.0, 0.0, 0.0], [0.0, 1.0, 1.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]];
+    let slope = Vec3A::new(-1.0, 1.0, 0.0).normalize();
+
+    let flat = load("roof_flat", &roof, TRIANGLE_STRIP, false);
+    assert!((normal_at(&flat, -0.01, 0.5) - slope).length() < 1e-5);
+
+    // Both slopes have the same angle at the ridge, so it points straight up
+    let smooth = load("roof_smooth", &roof, TRIANGLE_STRIP, true);
+    let normal = normal_at(&smooth, -0.01, 0.5);
+    assert!(normal.x > -0.05 && normal.x < 0.0, "{:?}", normal);
+    assert!((normal_at(&smooth, -0.99, 0.005) - slope).length() < 0.05);
+}
+
+// Quad in the y = 0 plane facing up, u along +x and v along +z, so down the image is +z
+const QUAD: [[f32; 3]; 4] = [[0.0, 0.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [1.0, 0.0, 1.0]];
+const QUAD_UVS: [f32; 8] = [0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 1.0];
+
+#[test]
+fn tangents_are_generated_along_the_uvs() {
+    let scene = load_attributes("generated_tangents", &QUAD, &[("TEXCOORD_0", "VEC2", QUAD_UVS.to_vec())], TRIANGLE_STRIP, false);
+    for (x, z) in [(0.2, 0.2), (0.8, 0.8)] {
+        let hit_result = hit_at(&scene, x, z);
+        assert!((hit_result.tangent - Vec3A::X).length() < 1e-5, "{:?}", hit_result.tangent);
+        // Green points up the image
+        assert!((hit_result.binormal + Vec3A::Z).length() < 1e-5, "{:?}", hit_result.binormal);
+    }
+
+    // Mirrored UVs flip the bitangent but keep the tangent along u
+    let mirrored: Vec<f32> = QUAD_UVS.chunks(2).flat_map(|uv| [uv[0], 1.0 - uv[1]]).collect();
+    let scene = load_attributes("mirrored_tangents", &QUAD, &[("TEXCOORD_0", "VEC2", mirrored)], TRIANGLE_STRIP, false);
+    let hit_result = hit_at(&scene, 0.2, 0.2);
+    assert!((hit_result.tangent - Vec3A::X).length() < 1e-5);
+    assert!((hit_result.binormal - Vec3A::Z).length() < 1e-5);
+}
+
+#[test]
+fn degenerate_uvs_still_get_a_frame() {
+    let scene = load_attributes("degenerate_tangents", &QUAD, &[("TEXCOORD_0", "VEC2", vec![0.5; 8])], TRIANGLE_STRIP, false);
+    let hit_result = hit_at(&scene, 0.2, 0.2);
+    assert!(hit_result.tangent.is_finite() && hit_result.binormal.is_finite());
+    assert!(hit_result.tangent.dot(hit_result.normal).abs() < 1e-5);
+    assert!((hit_result.tangent.length() - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn provided_tangents_are_used_with_their_sign() {
+    let tangents = [0.0, 0.0, 1.0, -1.0].repeat(4);
+    let scene = load_attributes("provided_tangents", &QUAD, &[
+        ("TEXCOORD_0", "VEC2", QUAD_UVS.to_vec()),
+        ("TANGENT", "VEC4", tangents),
+    ], TRIANGLE_STRIP, false);
+    let hit_result = hit_at(&scene, 0.2, 0.2);
+    assert!((hit_result.tangent - Vec3A::Z).length() < 1e-5, "{:?}", hit_result.tangent);
+    // cross(normal, tangent) * w
+    assert!((hit_result.binormal - Vec3A::Y.cross(Vec3A::Z) * -1.0).length() < 1e-5, "{:?}", hit_result.binormal);
+}
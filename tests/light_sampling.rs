@@ -0,0 +1,29 @@
+// Points sampled on lights lie on their surface, whether seen from outside or inside.
+
+use std::sync::Arc;
+
+use glam::Vec3A;
+use pupsy_render::engine::geometry::sphere::Sphere;
+use pupsy_render::engine::geometry::traceable::Traceable;
+use pupsy_render::engine::material::diffuse::DiffuseMaterial;
+use pupsy_render::engine::math::ray::{Ray, RayCone};
+use pupsy_render::engine::sampler::*;
+
+#[test]
+fn sphere_samples_are_on_the_surface() {
+    let sphere = Sphere::new(Arc::new(DiffuseMaterial{}), 2.0, Vec3A::new(1.0, 0.0, 0.0));
+    let mut sampler = SamplerType::Independent.create(0, 1000);
+
+    for eye in [Vec3A::new(1.0, 0.0, 10.0), Vec3A::new(1.5, 0.5, 0.0)] {
+        for i in 0..1000 {
+            sampler.start_sample(i, 0, 0);
+            let point = sphere.random(eye, sampler.as_mut());
+            assert!(((point - sphere.position).length() - 2.0).abs() < 1e-3, "{:?}", point);
+
+            // And it is what a ray from `eye` towards it hits first
+            let ray = Ray{origin: eye, direction: (point - eye).normalize(), cone: RayCone::ZERO};
+            let (hit_result, _) = sphere.hit(&ray, 0.001, f32::MAX);
+            assert!((hit_result.unwrap().t - (point - eye).length()).abs() < 1e-2);
+        }
+    }
+}
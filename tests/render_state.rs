@@ -0,0 +1,125 @@
+// Renders interrupted at any point resume to exactly what an uninterrupted render accumulates.
+
+use std::fs;
+use std::sync::Arc;
+
+use glam::{Mat4, Vec3, Vec3A};
+use pupsy_render::engine::camera::PerspectiveCamera;
+use pupsy_render::engine::environment::*;
+use pupsy_render::engine::geometry::sphere::Sphere;
+use pupsy_render::engine::material::pbr::PBRMaterial;
+use pupsy_render::engine::render_context::RenderContext;
+use pupsy_render::engine::render_state::*;
+use pupsy_render::engine::renderer::Renderer;
+use pupsy_render::engine::sampler::SamplerType;
+use pupsy_render::engine::scene::Scene;
+
+fn path(name: &str) -> String {
+    std::env::temp_dir().join(name).to_string_lossy().into_owned()
+}
+
+// 40 pixels high so the image is split over several tiles
+fn context(name: &str, spp: u32) -> RenderContext {
+    let mut material = PBRMaterial::new();
+    material.pbr_metallic_roughness.roughness_factor = 0.4;
+
+    let mut scene = Scene::new();
+    scene.geometry.push(Arc::new(Sphere::new(Arc::new(material), 1.0, Vec3A::ZERO)));
+    scene.environment = Arc::new(EnvironmentMap::new(8, 4,
+        (0..32).map(|i| Vec3A::splat(i as f32 / 8.0)).collect(), 0.0, 1.0));
+    scene.build_bvh();
+
+    let mut render_context = RenderContext::new();
+    render_context.scene = Arc::new(scene);
+    render_context.spp = spp;
+    render_context.resolution = 40;
+    render_context.output = path(format!("pupsy_state_{}.exr", name).as_str());
+    render_context.state = Some(path(format!("pupsy_state_{}.state", name).as_str()));
+    render_context
+}
+
+fn camera(z: f32) -> Arc<PerspectiveCamera> {
+    Arc::new(PerspectiveCamera::new(&Mat4::from_translation(Vec3::new(0.0, 0.0, z)), 0.8, 1.0, 0.1, 100.0, "camera"))
+}
+
+fn render(render_context: RenderContext) -> Result<RenderState, RenderStateError> {
+    let state = render_context.state.clone().unwrap();
+    Renderer{}.render(camera(5.0), Arc::new(render_context))?;
+    RenderState::load(state.as_str())
+}
+
+#[test]
+fn interrupted_passes_resume_to_the_uninterrupted_render() {
+    let full = render(context("full", 16)).unwrap();
+
+    // Stopped in the pass from 4 to 16 samples, after the top half of the image was done
+    let mut interrupted = render(context("interrupted", 4)).unwrap();
+    assert_eq!(interrupted.samples_done, 4);
+    let half = interrupted.frame_buffer.samples.len() / 2;
+    interrupted.frame_buffer.color[..half].copy_from_slice(&full.frame_buffer.color[..half]);
+    interrupted.frame_buffer.samples[..half].copy_from_slice(&full.frame_buffer.samples[..half]);
+    interrupted.frame_buffer.luminance_squared[..half].copy_from_slice(&full.frame_buffer.luminance_squared[..half]);
+    interrupted.save(path("pupsy_state_interrupted.state").as_str()).unwrap();
+
+    let mut resumed = context("interrupted", 16);
+    resumed.resume = resumed.state.clone();
+    let resumed = render(resumed).unwrap();
+
+    assert!(resumed.frame_buffer.samples.iter().all(|samples| *samples == 16));
+    assert_eq!(resumed.frame_buffer.samples, full.frame_buffer.samples);
+    assert_eq!(resumed.frame_buffer.color, full.frame_buffer.color);
+    assert_eq!(resumed.frame_buffer.luminance_squared, full.frame_buffer.luminance_squared);
+}
+
+#[test]
+fn states_of_other_cameras_are_rejected() {
+    render(context("camera", 1)).unwrap();
+
+    let mut resumed = context("camera", 4);
+    resumed.resume = resumed.state.clone();
+    let result = Renderer{}.render(camera(6.0), Arc::new(resumed));
+    assert!(matches!(result, Err(RenderStateError::Mismatch(_))), "{:?}", result);
+}
+
+#[test]
+fn stratified_states_only_resume_at_their_sample_count() {
+    let mut stratified = context("stratified", 4);
+    stratified.sampler = SamplerType::Stratified;
+    render(stratified).unwrap();
+
+    // The strata of 16 samples per pixel are laid out differently from those of 4
+    let mut resumed = context("stratified", 16);
+    resumed.sampler = SamplerType::Stratified;
+    resumed.resume = resumed.state.clone();
+    let result = Renderer{}.render(camera(5.0), Arc::new(resumed));
+    assert!(matches!(result, Err(RenderStateError::Mismatch(_))), "{:?}", result);
+
+    let mut resumed = context("stratified", 4);
+    resumed.sampler = SamplerType::Stratified;
+    resumed.resume = resumed.state.clone();
+    assert!(render(resumed).is_ok());
+}
+
+#[test]
+fn sizes_not_matching_the_pixel_data_are_format_errors() {
+    let state = path("pupsy_state_size.state");
+    render(context("size", 1)).unwrap();
+    let data = fs::read(state.as_str()).unwrap();
+
+    // Height after the magic, version, hash, seed, pass index, samples done, pass in progress and width
+    let mut huge = data.clone();
+    huge[44..48].copy_from_slice(&u32::MAX.to_le_bytes());
+    fs::write(state.as_str(), huge).unwrap();
+    assert!(matches!(RenderState::load(state.as_str()), Err(RenderStateError::Format(_))));
+
+    fs::write(state.as_str(), &data[..data.len() - 1]).unwrap();
+    assert!(matches!(RenderState::load(state.as_str()), Err(RenderStateError::Format(_))));
+}
+
+#[test]
+fn unwritable_outputs_are_errors() {
+    let mut render_context = context("unwritable", 1);
+    render_context.output = String::from("/nonexistent/pupsy_state_unwritable.exr");
+    let result = Renderer{}.render(camera(5.0), Arc::new(render_context));
+    assert!(matches!(result, Err(RenderStateError::Output(_))), "{:?}", result);
+}
@@ -0,0 +1,77 @@
+// Distribution checks for the sample generators: every dimension has to be uniform, and the
+// low-discrepancy ones have to keep their stratification per pixel.
+
+use glam::{Vec2};
+use pupsy_render::engine::sampler::*;
+
+const ALL: [SamplerType; 4] = [SamplerType::Independent, SamplerType::Stratified, SamplerType::Halton, SamplerType::Sobol];
+
+// The first `count` values of the 2D dimension pair `dimension` for one pixel
+fn points(sampler: SamplerType, count: u32, dimension: u32, x: u32, y: u32) -> Vec<Vec2> {
+    let mut generator = sampler.create(3, count);
+    (0..count).map(|sample_index| {
+        generator.start_sample(x, y, sample_index);
+        for _ in 0..dimension {
+            generator.get_2d();
+        }
+        generator.get_2d()
+    }).collect()
+}
+
+// Samples falling in every cell of a `resolution` x `resolution` grid
+fn cell_counts(points: &[Vec2], resolution_x: u32, resolution_y: u32) -> Vec<u32> {
+    let mut counts = vec![0; (resolution_x * resolution_y) as usize];
+    for point in points {
+        let x = (point.x * resolution_x as f32) as u32;
+        let y = (point.y * resolution_y as f32) as u32;
+        counts[(y * resolution_x + x) as usize] += 1;
+    }
+    counts
+}
+
+#[test]
+fn values_are_in_unit_interval_and_uniform() {
+    for sampler in ALL {
+        let mut sum = 0.0;
+        let mut count = 0;
+        for x in 0..16 {
+            for point in points(sampler, 64, 5, x, 1) {
+                assert!(point.cmpge(Vec2::ZERO).all() && point.cmplt(Vec2::ONE).all(), "{} {:?}", sampler.name(), point);
+                sum += point.x + point.y;
+                count += 2;
+            }
+        }
+
+        let mean = sum / count as f32;
+        assert!((mean - 0.5).abs() < 0.02, "{} mean {}", sampler.name(), mean);
+    }
+}
+
+#[test]
+fn sobol_pairs_are_elementary_intervals() {
+    // Owen-scrambled Sobol pairs are (0, 2)-sequences: 16 points put one sample in every
+    // 16x1, 8x2, 4x4, 2x8 and 1x16 cell, whatever the pixel or dimension
+    for dimension in [0, 1, 7] {
+        let points = points(SamplerType::Sobol, 16, dimension, 5, 9);
+        for resolution_x in [1, 2, 4, 8, 16] {
+            let counts = cell_counts(&points, resolution_x, 16 / resolution_x);
+            assert!(counts.iter().all(|count| *count == 1), "dimension {} {}x{}: {:?}",
+                dimension, resolution_x, 16 / resolution_x, counts);
+        }
+    }
+}
+
+#[test]
+fn stratified_fills_every_stratum() {
+    for dimension in [0, 3] {
+        let points = points(SamplerType::Stratified, 16, dimension, 2, 4);
+        assert!(cell_counts(&points, 4, 4).iter().all(|count| *count == 1));
+    }
+}
+
+#[test]
+fn pixels_are_decorrelated() {
+    for sampler in ALL {
+        assert_ne!(points(sampler, 4, 0, 0, 0), points(sampler, 4, 0, 1, 0), "{}", sampler.name());
+    }
+}
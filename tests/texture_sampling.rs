@@ -0,0 +1,216 @@
+// Texture lookups have to follow the glTF sampler (filters and wrap modes) and filter over the
+// footprint of the ray cone.
+
+use std::sync::Arc;
+
+use glam::{Vec2, Vec3A, Vec4};
+use image::{DynamicImage, GrayImage, ImageBuffer, Luma, LumaA, Rgb, RgbImage, Rgba, RgbaImage};
+use image::hdr::HdrEncoder;
+use pupsy_render::engine::geometry::traceable::Traceable;
+use pupsy_render::engine::geometry::triangle::Triangle;
+use pupsy_render::engine::geometry::vertex::Vertex;
+use pupsy_render::engine::material::diffuse::DiffuseMaterial;
+use pupsy_render::engine::math::ray::{Ray, RayCone};
+use pupsy_render::engine::sampler::sampler::*;
+use pupsy_render::engine::texture::{ColorSpace, Texture};
+use pupsy_render::engine::texture::texture2d::Texture2D;
+
+// 2x1 texture, black on the left and white on the right
+fn texture() -> Texture2D {
+    let mut image = RgbaImage::new(2, 1);
+    image.put_pixel(0, 0, Rgba([0, 0, 0, 255]));
+    image.put_pixel(1, 0, Rgba([255, 255, 255, 255]));
+    Texture2D::new(Texture::new(vec![2, 1], 1, 4, DynamicImage::ImageRgba8(image)))
+}
+
+fn sampler(filter: Filter, wrap: WrapMode) -> Sampler {
+    let mut sampler = Sampler::new();
+    sampler.mag_filter = filter;
+    sampler.min_filter = filter;
+    sampler.wrap_s = wrap;
+    sampler.wrap_t = wrap;
+    sampler
+}
+
+fn red(texture: &Texture2D, sampler: &Sampler, u: f32) -> f32 {
+    texture.sample(sampler, Vec2::new(u, 0.5)).x
+}
+
+fn assert_close(value: f32, expected: f32) {
+    assert!((value - expected).abs() < 1e-4, "{} != {}", value, expected);
+}
+
+#[test]
+fn nearest_picks_the_covering_texel() {
+    let texture = texture();
+    let sampler = sampler(Filter::Nearest, WrapMode::Repeat);
+    assert_close(red(&texture, &sampler, 0.3), 0.0);
+    assert_close(red(&texture, &sampler, 0.7), 1.0);
+    assert_eq!(texture.sample(&sampler, Vec2::new(0.7, 0.5)), Vec4::ONE);
+}
+
+#[test]
+fn linear_interpolates_between_texel_centers() {
+    let texture = texture();
+    let sampler = sampler(Filter::Linear, WrapMode::ClampToEdge);
+    assert_close(red(&texture, &sampler, 0.25), 0.0);
+    assert_close(red(&texture, &sampler, 0.5), 0.5);
+    assert_close(red(&texture, &sampler, 0.625), 0.75);
+    assert_close(red(&texture, &sampler, 0.75), 1.0);
+}
+
+#[test]
+fn wrap_modes() {
+    let texture = texture();
+
+    // Past the right edge: repeat blends back into the black texel, clamp stays white
+    assert_close(red(&texture, &sampler(Filter::Linear, WrapMode::Repeat), 1.0), 0.5);
+    assert_close(red(&texture, &sampler(Filter::Linear, WrapMode::ClampToEdge), 1.0), 1.0);
+    assert_close(red(&texture, &sampler(Filter::Linear, WrapMode::MirroredRepeat), 1.0), 1.0);
+
+    // One texture width further, mirrored repeat is flipped and repeat is not
+    assert_close(red(&texture, &sampler(Filter::Nearest, WrapMode::Repeat), 1.3), 0.0);
+    assert_close(red(&texture, &sampler(Filter::Nearest, WrapMode::MirroredRepeat), 1.3), 1.0);
+    assert_close(red(&texture, &sampler(Filter::Nearest, WrapMode::ClampToEdge), -0.5), 0.0);
+}
+
+// Texture alternating between black and white columns
+fn stripes(size: u32) -> Texture2D {
+    let mut image = RgbaImage::new(size, size);
+    for (x, _, pixel) in image.enumerate_pixels_mut() {
+        let value = if x % 2 == 0 {0} else {255};
+        *pixel = Rgba([value, value, value, 255]);
+    }
+    Texture2D::new(Texture::new(vec![size, size], 1, 4, DynamicImage::ImageRgba8(image)))
+}
+
+#[test]
+fn minification_reads_the_mip_pyramid() {
+    let texture = stripes(16);
+    let mut sampler = sampler(Filter::Linear, WrapMode::Repeat);
+
+    // A footprint of four texels averages the stripes, without mipmaps the base level aliases
+    let footprint = [Vec2::new(0.25, 0.0), Vec2::new(0.0, 0.25)];
+    let uv = Vec2::new(0.5 / 16.0, 0.5);
+    assert_close(texture.sample_footprint(&sampler, uv, footprint).x, 0.5);
+
+    sampler.mipmap_filter = None;
+    assert_close(texture.sample_footprint(&sampler, uv, footprint).x, 0.0);
+}
+
+#[test]
+fn anisotropic_footprint_keeps_detail_across_the_minor_axis() {
+    let texture = stripes(16);
+    let sampler = sampler(Filter::Nearest, WrapMode::Repeat);
+
+    // Stretched along the stripes, narrow across them
+    let footprint = [Vec2::new(0.0, 0.5), Vec2::new(0.01, 0.0)];
+    assert_close(texture.sample_footprint(&sampler, Vec2::new(0.5 / 16.0, 0.5), footprint).x, 0.0);
+    assert_close(texture.sample_footprint(&sampler, Vec2::new(1.5 / 16.0, 0.5), footprint).x, 1.0);
+}
+
+#[test]
+fn triangle_hits_carry_the_ray_cone_footprint() {
+    let vertex = |x: f32, y: f32| Vertex::new(Vec3A::new(x, y, 0.0), Vec3A::Z, Vec3A::Y, Vec3A::X,
+        vec![Vec3A::new(x / 2.0, y / 2.0, 0.0)]);
+    let triangle = Triangle::new(Arc::new(DiffuseMaterial{}), vertex(0.0, 0.0), vertex(2.0, 0.0), vertex(0.0, 2.0));
+
+    // Width 0.1 after ten units, half that in UV space
+    let ray = Ray{origin: Vec3A::new(0.5, 0.5, -10.0), direction: Vec3A::Z, cone: RayCone{width: 0.0, spread: 0.01}};
+    let mut hit_result = triangle.hit(&ray, 0.001, f32::MAX).0.unwrap();
+    assert!(hit_result.uv_footprints.is_empty());
+    triangle.finalize(&ray, &mut hit_result);
+    let footprint = hit_result.uv_footprints[0];
+
+    assert_close(footprint[0].length(), 0.05);
+    assert_close(footprint[1].length(), 0.05);
+    assert_close(footprint[0].dot(footprint[1]), 0.0);
+}
+
+#[test]
+fn footprints_follow_the_uv_sets_the_vertices_have() {
+    // Only TEXCOORD_1, stored as the first entry with its set number in z
+    let vertex = |x: f32, y: f32| Vertex::new(Vec3A::new(x, y, 0.0), Vec3A::Z, Vec3A::Y, Vec3A::X,
+        vec![Vec3A::new(x / 2.0, y / 2.0, 1.0)]);
+    let triangle = Triangle::new(Arc::new(DiffuseMaterial{}), vertex(0.0, 0.0), vertex(2.0, 0.0), vertex(0.0, 2.0));
+
+    let ray = Ray{origin: Vec3A::new(0.5, 0.5, -10.0), direction: Vec3A::Z, cone: RayCone{width: 0.0, spread: 0.01}};
+    let mut hit_result = triangle.hit(&ray, 0.001, f32::MAX).0.unwrap();
+    triangle.finalize(&ray, &mut hit_result);
+
+    assert_eq!(hit_result.uvs, vec![Vec3A::new(0.25, 0.25, 1.0)]);
+    assert_eq!(hit_result.uv_footprints.len(), 1);
+    assert_close(hit_result.uv_footprints[0][0].length(), 0.05);
+}
+
+fn single_texel(image: DynamicImage) -> Texture {
+    Texture::new(vec![1, 1], 1, 4, image)
+}
+
+#[test]
+fn integer_formats_are_normalized_by_bit_depth() {
+    let sampler = Sampler::new();
+    let uv = Vec2::splat(0.5);
+
+    let rgb8 = Texture2D::new(single_texel(DynamicImage::ImageRgb8(RgbImage::from_pixel(1, 1, Rgb([255, 0, 51])))));
+    assert_eq!(rgb8.sample(&sampler, uv), Vec4::new(1.0, 0.0, 0.2, 1.0));
+
+    let rgb16 = Texture2D::new(single_texel(DynamicImage::ImageRgb16(
+        ImageBuffer::from_pixel(1, 1, Rgb([65535u16, 0, 13107])))));
+    assert_eq!(rgb16.sample(&sampler, uv), Vec4::new(1.0, 0.0, 0.2, 1.0));
+
+    let gray_alpha16 = Texture2D::new(single_texel(DynamicImage::ImageLumaA16(
+        ImageBuffer::from_pixel(1, 1, LumaA([32768u16, 65535])))));
+    let value = gray_alpha16.sample(&sampler, uv);
+    assert_close(value.x, 0.5);
+    assert_eq!((value.x, value.w), (value.z, 1.0));
+}
+
+#[test]
+fn srgb_textures_are_decoded_before_filtering() {
+    let sampler = Sampler::new();
+    let uv = Vec2::splat(0.5);
+
+    let mut texture = single_texel(DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([188, 188, 188, 128]))));
+    texture.set_color_space(ColorSpace::SRGB);
+    let value = Texture2D::new(texture.clone()).sample(&sampler, uv);
+    assert!((value.x - 0.5).abs() < 0.005, "{}", value.x);
+    assert_close(value.w, 128.0 / 255.0);
+
+    texture.set_color_space(ColorSpace::Linear);
+    assert_close(Texture2D::new(texture).sample(&sampler, uv).x, 188.0 / 255.0);
+
+    // Black and white sRGB stripes average to half the linear intensity
+    let mut stripes = stripes(16).texture;
+    stripes.set_color_space(ColorSpace::SRGB);
+    let footprint = [Vec2::new(0.25, 0.0), Vec2::new(0.0, 0.25)];
+    assert_close(Texture2D::new(stripes).sample_footprint(&sampler, Vec2::new(0.5 / 16.0, 0.5), footprint).x, 0.5);
+}
+
+#[test]
+fn color_space_changes_keep_the_stored_values() {
+    let sampler = sampler(Filter::Nearest, WrapMode::ClampToEdge);
+    let mut texture = Texture::new(vec![256, 1], 1, 1,
+        DynamicImage::ImageLuma8(GrayImage::from_fn(256, 1, |x, _| Luma([x as u8]))));
+
+    // Every 8 bit value reads back exactly after a round trip through sRGB
+    texture.set_color_space(ColorSpace::SRGB);
+    texture.set_color_space(ColorSpace::Linear);
+    let texture = Texture2D::new(texture);
+    for value in 0..256 {
+        let texel = texture.sample(&sampler, Vec2::new((value as f32 + 0.5) / 256.0, 0.5));
+        assert_eq!(texel, Vec4::new(value as f32 / 255.0, value as f32 / 255.0, value as f32 / 255.0, 1.0));
+    }
+}
+
+#[test]
+fn float_textures_keep_their_range() {
+    let sampler = Sampler::new();
+
+    let mut data = Vec::new();
+    HdrEncoder::new(&mut data).encode(&[Rgb([4.0f32, 0.5, 0.0])], 1, 1).unwrap();
+
+    let mut texture = Texture::load_hdr(&data).unwrap();
+    texture.set_color_space(ColorSpace::SRGB);
+    assert_eq!(Texture2D::new(texture).sample(&sampler, Vec2::splat(0.5)), Vec4::new(4.0, 0.5, 0.0, 1.0));
+}
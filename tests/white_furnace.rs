@@ -0,0 +1,94 @@
+// White furnace: a white, non-absorbing object inside a uniform unit environment has to
+// reflect exactly the light it receives, so every pixel converges to one.
+
+use std::sync::Arc;
+
+use glam::{Vec3A};
+use pupsy_render::engine::environment::*;
+use pupsy_render::engine::geometry::sphere::Sphere;
+use pupsy_render::engine::material::Material;
+use pupsy_render::engine::material::diffuse::DiffuseMaterial;
+use pupsy_render::engine::material::pbr::PBRMaterial;
+use pupsy_render::engine::math::ray::{Ray, RayCone};
+use pupsy_render::engine::renderer::Renderer;
+use pupsy_render::engine::sampler::*;
+use pupsy_render::engine::scene::Scene;
+
+const SAMPLES: u32 = 20000;
+const DEPTH: u32 = 16;
+
+fn furnace_scene(material: Arc<dyn Material>, environment: Arc<dyn Environment>) -> Scene {
+    let mut scene = Scene::new();
+    scene.geometry.push(Arc::new(Sphere::new(material, 1.0, Vec3A::ZERO)));
+    scene.environment = environment;
+    scene.build_bvh();
+    scene
+}
+
+fn average_radiance(scene: &Scene, russian_roulette_depth: u32) -> Vec3A {
+    let mut sampler = SamplerType::Independent.create(0, SAMPLES);
+    let mut radiance = Vec3A::ZERO;
+    for i in 0..SAMPLES {
+        // Spread the camera rays over the visible part of the sphere
+        let offset = (i as f32 / SAMPLES as f32 - 0.5) * 1.6;
+        let ray = Ray{origin: Vec3A::new(offset, 0.0, -5.0), direction: Vec3A::Z, cone: RayCone::ZERO};
+        sampler.start_sample(i, 0, 0);
+        radiance += Renderer::sample_scene(&ray, scene, DEPTH, russian_roulette_depth, sampler.as_mut());
+    }
+
+    radiance / SAMPLES as f32
+}
+
+fn uniform_environment_map() -> Arc<dyn Environment> {
+    Arc::new(EnvironmentMap::new(16, 8, vec![Vec3A::ONE; 16 * 8], 0.0, 1.0))
+}
+
+#[test]
+fn diffuse_constant_environment() {
+    let scene = furnace_scene(Arc::new(DiffuseMaterial{}),
+        Arc::new(ConstantEnvironment{color: Vec3A::ONE}));
+
+    let radiance = average_radiance(&scene, DEPTH);
+    assert!((radiance - Vec3A::ONE).abs().max_element() < 1e-3, "{:?}", radiance);
+}
+
+#[test]
+fn diffuse_importance_sampled_environment() {
+    let scene = furnace_scene(Arc::new(DiffuseMaterial{}), uniform_environment_map());
+
+    let radiance = average_radiance(&scene, DEPTH);
+    assert!((radiance - Vec3A::ONE).abs().max_element() < 0.03, "{:?}", radiance);
+}
+
+#[test]
+fn pbr_does_not_create_energy() {
+    let mut material = PBRMaterial::new();
+    material.pbr_metallic_roughness.roughness_factor = 0.5;
+    material.pbr_metallic_roughness.metalic_factor = 1.0;
+
+    let scene = furnace_scene(Arc::new(material), uniform_environment_map());
+
+    let radiance = average_radiance(&scene, DEPTH);
+    assert!(radiance.max_element() < 1.03, "{:?}", radiance);
+    assert!(radiance.min_element() > 0.8, "{:?}", radiance);
+
+    // Rough white dielectric, the diffuse lobe only loses what the single scattered specular misses
+    let mut material = PBRMaterial::new();
+    material.pbr_metallic_roughness.roughness_factor = 1.0;
+    material.pbr_metallic_roughness.metalic_factor = 0.0;
+
+    let scene = furnace_scene(Arc::new(material), uniform_environment_map());
+
+    let radiance = average_radiance(&scene, DEPTH);
+    assert!(radiance.max_element() < 1.03, "{:?}", radiance);
+    assert!(radiance.min_element() > 0.9, "{:?}", radiance);
+}
+
+#[test]
+fn russian_roulette_is_unbiased() {
+    let scene = furnace_scene(Arc::new(DiffuseMaterial{}),
+        Arc::new(ConstantEnvironment{color: Vec3A::ONE}));
+
+    let radiance = average_radiance(&scene, 0);
+    assert!((radiance - Vec3A::ONE).abs().max_element() < 0.03, "{:?}", radiance);
+}